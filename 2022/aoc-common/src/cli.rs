@@ -0,0 +1,166 @@
+use std::{fmt::Display, str::FromStr};
+
+/// Check if only the answers should be printed, which is the case if the `--answers-only` or
+/// `--quiet` flag is passed, or if the answers are printed as JSON.
+pub fn answers_only() -> bool {
+    json_output() || std::env::args().any(|arg| arg == "--answers-only" || arg == "--quiet")
+}
+
+/// Check if the answers should be printed as a JSON object, which is the case if the `--json`
+/// flag is passed.
+fn json_output() -> bool {
+    std::env::args().any(|arg| arg == "--json")
+}
+
+/// Check if parse errors should be reported along with the lines around them, which is the case
+/// if the `--verbose` flag is passed.
+pub fn verbose() -> bool {
+    std::env::args().any(|arg| arg == "--verbose")
+}
+
+/// Check if the given flag asking for extra output is passed. The flag is ignored if only the
+/// answers should be printed.
+pub fn extra_output(flag: &str) -> bool {
+    !answers_only() && std::env::args().any(|arg| arg == flag)
+}
+
+/// Get the value following the given flag in the arguments. Returns `None` if the flag isn't
+/// passed, and `Some(None)` if the flag is the last argument or is followed by another flag.
+fn flag_value_in(args: &[String], flag: &str) -> Option<Option<String>> {
+    let position = args.iter().position(|arg| arg == flag)?;
+
+    Some(
+        args.get(position + 1)
+            .filter(|value| !value.starts_with("--"))
+            .cloned(),
+    )
+}
+
+/// Parse the value following the given flag in the arguments, or use the default if the flag
+/// isn't passed. A flag without a value or with a value that doesn't parse is an error.
+fn parse_flag_in<T: FromStr>(args: &[String], flag: &str, default: T) -> Result<T, String> {
+    match flag_value_in(args, flag) {
+        None => Ok(default),
+        Some(None) => Err(format!("Missing value after {flag}")),
+        Some(Some(value)) => value
+            .parse()
+            .map_err(|_| format!("Invalid value \"{value}\" for {flag}")),
+    }
+}
+
+/// Get the value passed after the given flag. Returns `None` if the flag isn't passed, and
+/// `Some(None)` if the flag is the last argument or is followed by another flag.
+pub fn flag_value(flag: &str) -> Option<Option<String>> {
+    flag_value_in(&std::env::args().collect::<Vec<_>>(), flag)
+}
+
+/// Parse the value passed after the given flag, or use the default if the flag isn't passed. A
+/// flag without a value or with a value that doesn't parse is an error.
+pub fn parse_flag<T: FromStr>(flag: &str, default: T) -> Result<T, String> {
+    parse_flag_in(&std::env::args().collect::<Vec<_>>(), flag, default)
+}
+
+/// Get the name of the input file. Use the sample input instead of the puzzle input if the
+/// `--example` flag is passed, exiting with an error if there is no sample input for the day.
+pub fn input_filename() -> &'static str {
+    if !std::env::args().any(|arg| arg == "--example") {
+        return "input.txt";
+    }
+
+    if !std::path::Path::new("sample.txt").exists() {
+        eprintln!("There is no sample input (sample.txt) for this day.");
+        std::process::exit(1);
+    }
+
+    "sample.txt"
+}
+
+/// Quote a string as a JSON string, escaping the quotes, backslashes and control characters.
+fn json_string(string: &str) -> String {
+    let mut json = String::with_capacity(string.len() + 2);
+    json.push('"');
+
+    for char in string.chars() {
+        match char {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            char if char.is_control() => json.push_str(&format!("\\u{:04x}", char as u32)),
+            char => json.push(char),
+        }
+    }
+
+    json.push('"');
+    json
+}
+
+/// Print the answers to both parts of the given day, one per line, or as a JSON object if the
+/// `--json` flag is passed.
+pub fn print_answers(day: u32, part1: impl Display, part2: impl Display) {
+    if !json_output() {
+        println!("{part1}");
+        println!("{part2}");
+        return;
+    }
+
+    println!(
+        "{{\"day\": {day}, \"part1\": {}, \"part2\": {}}}",
+        json_string(&part1.to_string()),
+        json_string(&part2.to_string())
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Turn the given arguments into owned strings.
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn flag_values_follow_their_flag() {
+        let args = args(&["day", "--top", "3", "--row", "-5", "--json", "--part"]);
+
+        assert_eq!(flag_value_in(&args, "--top"), Some(Some("3".to_string())));
+        assert_eq!(flag_value_in(&args, "--row"), Some(Some("-5".to_string())));
+        assert_eq!(flag_value_in(&args, "--json"), Some(None));
+        assert_eq!(flag_value_in(&args, "--part"), Some(None));
+        assert_eq!(flag_value_in(&args, "--view"), None);
+    }
+
+    #[test]
+    fn parsed_flags_reject_missing_and_invalid_values() {
+        let args = args(&["day", "--top", "3", "--row", "-5", "--bound", "x", "--part"]);
+
+        assert_eq!(parse_flag_in(&args, "--top", 2), Ok(3));
+        assert_eq!(parse_flag_in(&args, "--row", 10), Ok(-5));
+        assert_eq!(parse_flag_in(&args, "--view", 2), Ok(2));
+        assert_eq!(
+            parse_flag_in(&args, "--bound", 20),
+            Err("Invalid value \"x\" for --bound".to_string())
+        );
+        assert_eq!(
+            parse_flag_in(&args, "--part", 1),
+            Err("Missing value after --part".to_string())
+        );
+    }
+
+    #[test]
+    fn json_strings_escape_every_control_character() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(json_string("a \"b\" \\ c"), "\"a \\\"b\\\" \\\\ c\"");
+        assert_eq!(json_string("#.\n.#"), "\"#.\\n.#\"");
+        assert_eq!(
+            json_string("\r\t\u{0}\u{1b}\u{7f}"),
+            "\"\\r\\t\\u0000\\u001b\\u007f\""
+        );
+
+        // No control characters are left in the quoted string.
+        let all = (0..0x80).filter_map(char::from_u32).collect::<String>();
+        assert!(!json_string(&all).chars().any(|char| char.is_control()));
+    }
+}
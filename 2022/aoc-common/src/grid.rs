@@ -0,0 +1,120 @@
+/// A grid of cells along with its width and height.
+pub type Grid<T> = (Vec<Vec<T>>, (usize, usize));
+
+/// Parse a grid of characters, mapping each cell with its coordinates using `f`. Returns the grid
+/// along with its width and height, or an error if the lines are not all the same length.
+pub fn parse_grid<T, F: Fn(usize, usize, char) -> T>(input: &str, f: F) -> Result<Grid<T>, String> {
+    let grid = input
+        .lines()
+        .enumerate()
+        .map(|(y, line)| {
+            line.chars()
+                .enumerate()
+                .map(|(x, character)| f(x, y, character))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let width = grid.first().map_or(0, |row| row.len());
+
+    if let Some(y) = grid.iter().position(|row| row.len() != width) {
+        return Err(format!(
+            "Ragged grid: line {} has {} cells, expected {width}",
+            y + 1,
+            grid[y].len()
+        ));
+    }
+
+    let height = grid.len();
+
+    Ok((grid, (width, height)))
+}
+
+/// Transpose a grid so that its columns become rows. Every row of the grid is expected to have the
+/// same length.
+pub fn transpose<T: Clone>(grid: &[Vec<T>]) -> Vec<Vec<T>> {
+    let width = grid.first().map_or(0, |row| row.len());
+
+    (0..width)
+        .map(|x| grid.iter().map(|row| row[x].clone()).collect())
+        .collect()
+}
+
+/// Find the bounding box of a collection of coordinates, returning the minimum and maximum
+/// corners. Returns `None` if there are no coordinates.
+pub fn bounding_box<I: IntoIterator<Item = (i64, i64)>>(
+    coords: I,
+) -> Option<((i64, i64), (i64, i64))> {
+    coords
+        .into_iter()
+        .fold(None, |bounds, (a, b)| match bounds {
+            None => Some(((a, b), (a, b))),
+            Some(((min_a, min_b), (max_a, max_b))) => {
+                Some(((min_a.min(a), min_b.min(b)), (max_a.max(a), max_b.max(b))))
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_grid_with_its_dimensions() {
+        let (grid, dimensions) =
+            parse_grid("abc\ndef\n", |x, y, character| (x, y, character)).unwrap();
+
+        assert_eq!(dimensions, (3, 2));
+        assert_eq!(grid[0][0], (0, 0, 'a'));
+        assert_eq!(grid[1][2], (2, 1, 'f'));
+        assert_eq!(grid[0][1].2, 'b');
+    }
+
+    #[test]
+    fn rejects_a_ragged_grid() {
+        let error = parse_grid("abc\nde\n", |_, _, character| character).unwrap_err();
+
+        assert!(error.contains("line 2"), "{error}");
+    }
+
+    #[test]
+    fn transposes_a_non_square_grid() {
+        let grid = vec![vec![1, 2, 3], vec![4, 5, 6]];
+
+        let columns = transpose(&grid);
+
+        assert_eq!(columns.len(), 3);
+        assert!(columns.iter().all(|column| column.len() == 2));
+        assert_eq!(columns, vec![vec![1, 4], vec![2, 5], vec![3, 6]]);
+
+        for (y, row) in grid.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                assert_eq!(&columns[x][y], cell);
+            }
+        }
+
+        assert_eq!(transpose(&columns), grid);
+    }
+
+    #[test]
+    fn bounds_points_with_negative_coordinates() {
+        let points = [(3, -2), (-4, 1), (0, 0), (-1, -7), (2, 5)];
+
+        assert_eq!(bounding_box(points), Some(((-4, -7), (3, 5))));
+    }
+
+    #[test]
+    fn bounds_points_which_are_all_negative() {
+        // Seeding the bounds with the origin would wrongly stretch them to (0, 0).
+        assert_eq!(
+            bounding_box([(-3, -5), (-1, -2)]),
+            Some(((-3, -5), (-1, -2)))
+        );
+        assert_eq!(bounding_box([(-6, -6)]), Some(((-6, -6), (-6, -6))));
+    }
+
+    #[test]
+    fn has_no_bounds_without_points() {
+        assert_eq!(bounding_box([]), None);
+    }
+}
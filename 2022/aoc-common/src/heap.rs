@@ -0,0 +1,68 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// A priority queue which pops the item with the lowest priority first. Items with equal
+/// priorities are popped in ascending order.
+#[derive(Debug)]
+pub struct MinHeap<P: Ord, T: Ord> {
+    heap: BinaryHeap<Reverse<(P, T)>>,
+}
+
+impl<P: Ord, T: Ord> MinHeap<P, T> {
+    /// Create an empty priority queue.
+    pub fn new() -> Self {
+        Self {
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    /// Add an item to the queue with the given priority.
+    pub fn push(&mut self, priority: P, item: T) {
+        self.heap.push(Reverse((priority, item)));
+    }
+
+    /// Remove the item with the lowest priority from the queue.
+    pub fn pop(&mut self) -> Option<(P, T)> {
+        self.heap.pop().map(|Reverse(entry)| entry)
+    }
+}
+
+impl<P: Ord, T: Ord> Default for MinHeap<P, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pops_in_ascending_priority_order() {
+        let mut heap = MinHeap::new();
+
+        for (priority, item) in [(5, 'e'), (1, 'a'), (3, 'c'), (2, 'b'), (4, 'd')] {
+            heap.push(priority, item);
+        }
+
+        let popped = std::iter::from_fn(|| heap.pop()).collect::<Vec<_>>();
+
+        assert_eq!(popped, [(1, 'a'), (2, 'b'), (3, 'c'), (4, 'd'), (5, 'e')]);
+    }
+
+    #[test]
+    fn pops_equal_priorities_in_ascending_item_order() {
+        let mut heap = MinHeap::new();
+
+        heap.push(1, (2, 0));
+        heap.push(0, (9, 9));
+        heap.push(1, (0, 1));
+        heap.push(1, (0, 0));
+
+        assert_eq!(heap.pop(), Some((0, (9, 9))));
+        assert_eq!(heap.pop(), Some((1, (0, 0))));
+        assert_eq!(heap.pop(), Some((1, (0, 1))));
+        assert_eq!(heap.pop(), Some((1, (2, 0))));
+        assert_eq!(heap.pop(), None);
+    }
+}
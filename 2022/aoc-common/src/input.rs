@@ -0,0 +1,30 @@
+/// Normalize Windows line endings to `\n`, so parsers splitting on `\n` or `\n\n` don't see
+/// stray `\r` characters.
+pub fn normalize(input: &str) -> String {
+    input.replace("\r\n", "\n")
+}
+
+/// Read the input file into a string with normalized line endings.
+pub fn read_input(filename: &str) -> Result<String, String> {
+    std::fs::read_to_string(filename)
+        .map(|input| normalize(&input))
+        .map_err(|error| format!("Couldn't read {filename}: {error}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_windows_line_endings() {
+        assert_eq!(
+            normalize("1000\r\n2000\r\n\r\n4000\r\n"),
+            "1000\n2000\n\n4000\n"
+        );
+    }
+
+    #[test]
+    fn leaves_unix_line_endings_alone() {
+        assert_eq!(normalize("1000\n2000\n\n4000\n"), "1000\n2000\n\n4000\n");
+    }
+}
@@ -0,0 +1,57 @@
+use std::ops::RangeInclusive;
+
+/// Sort the ranges by their start and merge them into sorted, disjoint ranges. Ranges which
+/// overlap, are nested or are adjacent - like 1..=3 and 4..=6 - are merged together.
+pub fn merge_intervals(ranges: &mut [RangeInclusive<i64>]) -> Vec<RangeInclusive<i64>> {
+    ranges.sort_by_key(|range| *range.start());
+
+    let mut merged: Vec<RangeInclusive<i64>> = vec![];
+
+    for range in ranges.iter() {
+        match merged.last_mut() {
+            // If the range overlaps or is adjacent to the last merged range, extend the last one.
+            Some(last) if *range.start() <= last.end() + 1 => {
+                *last = *last.start()..=*range.end().max(last.end());
+            }
+            _ => merged.push(range.clone()),
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_no_ranges_into_none() {
+        assert_eq!(merge_intervals(&mut []), vec![]);
+    }
+
+    #[test]
+    fn keeps_a_single_range() {
+        assert_eq!(merge_intervals(&mut [-2..=5]), vec![-2..=5]);
+    }
+
+    #[test]
+    fn merges_nested_ranges_into_the_outer_one() {
+        assert_eq!(merge_intervals(&mut [3..=4, 0..=10, 5..=7]), vec![0..=10]);
+    }
+
+    #[test]
+    fn merges_chained_adjacent_ranges() {
+        assert_eq!(
+            merge_intervals(&mut [7..=9, 1..=3, 4..=6, 10..=10]),
+            vec![1..=10]
+        );
+    }
+
+    #[test]
+    fn keeps_ranges_with_a_gap_apart() {
+        assert_eq!(
+            merge_intervals(&mut [5..=8, 0..=3, 2..=4, 10..=12]),
+            vec![0..=8, 10..=12]
+        );
+    }
+}
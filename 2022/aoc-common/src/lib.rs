@@ -0,0 +1,22 @@
+//! Helpers shared by the solutions of the different days.
+
+mod cli;
+mod grid;
+mod heap;
+mod input;
+mod intervals;
+mod memo;
+mod parse;
+pub mod profile;
+mod search;
+
+pub use cli::{
+    answers_only, extra_output, flag_value, input_filename, parse_flag, print_answers, verbose,
+};
+pub use grid::{bounding_box, parse_grid, transpose, Grid};
+pub use heap::MinHeap;
+pub use input::{normalize, read_input};
+pub use intervals::merge_intervals;
+pub use memo::Memo;
+pub use parse::{parse_lines, report_error, ParseError};
+pub use search::flood_fill;
@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::profile;
+
+/// A memoization table for recursive calls. We want to reduce computation so we skip each
+/// invocation of an already seen set of inputs and return the outcome of that invocation.
+#[derive(Debug)]
+pub struct Memo<K, V> {
+    values: HashMap<K, V>,
+}
+
+impl<K: Eq + Hash, V: Copy> Memo<K, V> {
+    /// Create an empty memo.
+    pub fn new() -> Self {
+        Self {
+            values: HashMap::new(),
+        }
+    }
+
+    /// Get the value stored under the key, if there is one.
+    pub fn get(&self, key: &K) -> Option<V> {
+        let value = self.values.get(key).copied();
+
+        if value.is_some() {
+            profile::record_cache_hit();
+        }
+
+        value
+    }
+
+    /// Store the value under the key, for searches which decide themselves which values are worth
+    /// keeping.
+    pub fn insert(&mut self, key: K, value: V) {
+        profile::record_cache_insert();
+
+        self.values.insert(key, value);
+    }
+
+    /// Get the value stored under the key, or compute it with `f` and store it. The memo is passed
+    /// on to `f` so that it can recurse, and the value is only inserted once `f` returns, so no
+    /// borrow of the table is held while computing.
+    pub fn get_or_compute(&mut self, key: K, f: impl FnOnce(&mut Self) -> V) -> V {
+        if let Some(value) = self.values.get(&key) {
+            profile::record_cache_hit();
+
+            return *value;
+        }
+
+        let value = f(self);
+
+        profile::record_cache_insert();
+
+        self.values.insert(key, value);
+
+        value
+    }
+}
+
+impl<K: Eq + Hash, V: Copy> Default for Memo<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compute the n-th Fibonacci number through the memo, counting the computed values.
+    fn fibonacci(n: u64, memo: &mut Memo<u64, u64>, computed: &mut u64) -> u64 {
+        memo.get_or_compute(n, |memo| {
+            *computed += 1;
+
+            if n < 2 {
+                n
+            } else {
+                fibonacci(n - 1, memo, computed) + fibonacci(n - 2, memo, computed)
+            }
+        })
+    }
+
+    #[test]
+    fn computes_fibonacci_numbers_recursively() {
+        let mut memo = Memo::new();
+        let mut computed = 0;
+
+        assert_eq!(fibonacci(10, &mut memo, &mut computed), 55);
+        assert_eq!(
+            fibonacci(90, &mut memo, &mut computed),
+            2_880_067_194_370_816_120
+        );
+    }
+
+    #[test]
+    fn computes_each_value_only_once() {
+        let mut memo = Memo::new();
+        let mut computed = 0;
+
+        fibonacci(50, &mut memo, &mut computed);
+        assert_eq!(computed, 51);
+
+        // Everything up to 50 is cached, so asking again computes nothing new.
+        assert_eq!(fibonacci(50, &mut memo, &mut computed), 12_586_269_025);
+        assert_eq!(fibonacci(30, &mut memo, &mut computed), 832_040);
+        assert_eq!(computed, 51);
+    }
+}
@@ -0,0 +1,118 @@
+use std::{fmt::Display, str::FromStr};
+
+/// The number of lines shown before and after the offending line in a verbose error report.
+const CONTEXT_LINES: usize = 2;
+
+/// An error parsing a line of the input - the line number, starting at 1, and what went wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ParseError {
+    /// Create an error for the given line number, starting at 1.
+    pub fn new(line: usize, message: impl Display) -> Self {
+        Self {
+            line,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+/// Parse each line of the input into a `T`. Returns an error naming the first line which can't
+/// be parsed.
+pub fn parse_lines<T: FromStr>(input: &str) -> Result<Vec<T>, ParseError> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            line.parse().map_err(|_| ParseError {
+                line: index + 1,
+                message: format!("can't parse \"{line}\""),
+            })
+        })
+        .collect()
+}
+
+/// Describe a parse error of the given input. The concise report is the error on a single line,
+/// while the verbose one also shows the lines around the error, marking the offending one with
+/// `>`.
+pub fn report_error(error: &ParseError, input: &str, verbose: bool) -> String {
+    if !verbose {
+        return error.to_string();
+    }
+
+    let first = error.line.saturating_sub(CONTEXT_LINES).max(1);
+    let context = input
+        .lines()
+        .zip(1..)
+        .skip(first - 1)
+        .take_while(|(_, number)| *number <= error.line + CONTEXT_LINES)
+        .map(|(line, number)| {
+            let marker = if number == error.line { '>' } else { ' ' };
+
+            format!("{marker} {number:>4} | {line}")
+        });
+
+    std::iter::once(error.to_string())
+        .chain(context)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_line() {
+        assert_eq!(parse_lines::<i64>("1\n2\n-3\n0\n"), Ok(vec![1, 2, -3, 0]));
+    }
+
+    #[test]
+    fn reports_the_first_bad_line() {
+        assert_eq!(
+            parse_lines::<i64>("1\n2\nthree\n4\nfive\n"),
+            Err(ParseError {
+                line: 3,
+                message: "can't parse \"three\"".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn concise_report_is_a_single_line() {
+        let input = "1\n2\nthree\n4\n5\n6\n";
+        let error = parse_lines::<i64>(input).unwrap_err();
+
+        assert_eq!(
+            report_error(&error, input, false),
+            "Line 3: can't parse \"three\""
+        );
+    }
+
+    #[test]
+    fn verbose_report_shows_the_neighbouring_lines() {
+        let input = "1\n2\nthree\n4\n5\n6\n";
+        let error = parse_lines::<i64>(input).unwrap_err();
+
+        assert_eq!(
+            report_error(&error, input, true),
+            "Line 3: can't parse \"three\"\n     1 | 1\n     2 | 2\n>    3 | three\n     4 | 4\n     5 | 5"
+        );
+
+        // The context is cut short at the start and the end of the input.
+        let error = parse_lines::<i64>("one\n2\n").unwrap_err();
+        assert_eq!(
+            report_error(&error, "one\n2\n", true),
+            "Line 1: can't parse \"one\"\n>    1 | one\n     2 | 2"
+        );
+    }
+}
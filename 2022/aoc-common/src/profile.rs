@@ -0,0 +1,73 @@
+//! Counters for the recursive searches. They only count with the `profile` feature, otherwise
+//! every function is a no-op, so the searches pay nothing for them.
+
+#[cfg(feature = "profile")]
+use std::cell::Cell;
+
+#[cfg(feature = "profile")]
+thread_local! {
+    static CALLS: Cell<u64> = const { Cell::new(0) };
+    static CACHE_HITS: Cell<u64> = const { Cell::new(0) };
+    static CACHE_SIZE: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A snapshot of the counters of the current thread.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Counters {
+    pub calls: u64,
+    pub cache_hits: u64,
+    pub cache_size: u64,
+}
+
+/// Record an invocation of the recursive search.
+#[inline]
+pub fn record_call() {
+    #[cfg(feature = "profile")]
+    CALLS.with(|calls| calls.set(calls.get() + 1));
+}
+
+/// Record a cache hit in the recursive search.
+#[inline]
+pub fn record_cache_hit() {
+    #[cfg(feature = "profile")]
+    CACHE_HITS.with(|hits| hits.set(hits.get() + 1));
+}
+
+/// Record a new value stored in the cache of the recursive search.
+#[inline]
+pub fn record_cache_insert() {
+    #[cfg(feature = "profile")]
+    CACHE_SIZE.with(|size| size.set(size.get() + 1));
+}
+
+/// Get the counters of the current thread. They are all zero without the `profile` feature.
+pub fn counters() -> Counters {
+    #[cfg(feature = "profile")]
+    return Counters {
+        calls: CALLS.with(Cell::get),
+        cache_hits: CACHE_HITS.with(Cell::get),
+        cache_size: CACHE_SIZE.with(Cell::get),
+    };
+
+    #[cfg(not(feature = "profile"))]
+    Counters::default()
+}
+
+/// Set the counters of the current thread back to zero.
+pub fn reset() {
+    #[cfg(feature = "profile")]
+    for counter in [&CALLS, &CACHE_HITS, &CACHE_SIZE] {
+        counter.with(|count| count.set(0));
+    }
+}
+
+/// Print a summary of the counters to standard error, so the answers stay untouched.
+pub fn print_summary() {
+    let Counters {
+        calls,
+        cache_hits,
+        cache_size,
+    } = counters();
+
+    eprintln!("calls: {calls}, cache_hits: {cache_hits}, cache_size: {cache_size}");
+}
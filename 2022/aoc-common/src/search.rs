@@ -0,0 +1,87 @@
+use std::collections::{HashSet, VecDeque};
+use std::hash::Hash;
+
+/// Find all the nodes reachable from the start node, where `neighbors` yields the nodes which can
+/// be reached directly from a node. Use BFS to visit all nodes.
+pub fn flood_fill<N, FN, I>(start: N, mut neighbors: FN) -> HashSet<N>
+where
+    N: Eq + Hash + Copy,
+    FN: FnMut(&N) -> I,
+    I: IntoIterator<Item = N>,
+{
+    // Keep track of visited nodes.
+    let mut visited = HashSet::from([start]);
+    // Create a queue of nodes to visit.
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(next) = queue.pop_front() {
+        // Add the neighbours which weren't visited yet to the visited set and the queue.
+        for neighbor in neighbors(&next) {
+            if visited.insert(neighbor) {
+                queue.push_back(neighbor);
+            }
+        }
+    }
+
+    visited
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floods_the_region_around_an_obstacle() {
+        // A 4x3 region split by a wall of `#`, with the cell in the bottom right corner reachable
+        // only through the gap under the wall.
+        let region = ["..#.", "..#.", "...."];
+        let open = |(x, y): (i32, i32)| {
+            region
+                .get(y as usize)
+                .and_then(|row| row.as_bytes().get(x as usize))
+                .is_some_and(|cell| *cell == b'.')
+        };
+
+        let reachable = flood_fill((0, 0), |&(x, y)| {
+            [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                .into_iter()
+                .filter(|&cell| cell.0 >= 0 && cell.1 >= 0 && open(cell))
+        });
+
+        let expected = (0..4)
+            .flat_map(|x| (0..3).map(move |y| (x, y)))
+            .filter(|&cell| open(cell))
+            .collect::<HashSet<_>>();
+
+        assert_eq!(reachable.len(), 10);
+        assert_eq!(reachable, expected);
+        assert!(!reachable.contains(&(2, 0)));
+    }
+
+    #[test]
+    fn stops_at_a_closed_wall() {
+        // The wall reaches the bottom, so the right column is cut off.
+        let region = ["..#.", "..#.", "..#."];
+
+        let reachable = flood_fill((0usize, 0usize), |&(x, y)| {
+            [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ]
+            .into_iter()
+            .filter(|&(x, y)| {
+                region
+                    .get(y)
+                    .and_then(|row| row.as_bytes().get(x))
+                    .is_some_and(|cell| *cell == b'.')
+            })
+        });
+
+        assert_eq!(
+            reachable,
+            HashSet::from([(0, 0), (1, 0), (0, 1), (1, 1), (0, 2), (1, 2)])
+        );
+    }
+}
@@ -1,11 +1,12 @@
-/// Get the sum of calories for each of the elfs in the input file.
+use aoc_common::{input_filename, print_answers, read_input};
+
+/// Get the sum of calories for each of the elfs in the input.
 /// First split into strings by the empty line which separates elf entries.
 /// Then do some string cleanup to remove accidental double empty lines or whitespace characters.
 /// Then fold those lines into sums of calories by parsing each line as an unsigned 32 bit integer.
 /// Finally sort and reverse the vector.
-fn get_elf_calories(filename: &str) -> Vec<u32> {
-    let mut calories_per_elf = std::fs::read_to_string(filename)
-        .unwrap()
+fn parse_elf_calories(input: &str) -> Vec<u32> {
+    let mut calories_per_elf = input
         .split("\n\n")
         .filter_map(|lines| {
             let lines = lines.trim().to_string();
@@ -29,16 +30,39 @@ fn get_elf_calories(filename: &str) -> Vec<u32> {
     calories_per_elf
 }
 
+/// Get the sum of calories for each of the elfs in the input file, sorted from the most calories.
+fn get_elf_calories(filename: &str) -> Vec<u32> {
+    parse_elf_calories(&read_input(filename).unwrap())
+}
+
 fn main() {
     // Get the calories for each elf.
-    let elf_calories = get_elf_calories("./input.txt");
+    let elf_calories = get_elf_calories(input_filename());
 
     // Get the max calories of any elf.
-    let max_calories = elf_calories.get(0).unwrap();
+    let max_calories = elf_calories.first().unwrap();
 
     // Get the sum of the top three elfs.
     let sum_of_top_three: u32 = elf_calories.get(0..3).unwrap().iter().sum();
 
-    println!("{max_calories}");
-    println!("{sum_of_top_three}");
+    print_answers(1, max_calories, sum_of_top_three);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aoc_common::normalize;
+
+    const EXAMPLE: &str = "1000\n2000\n3000\n\n4000\n\n5000\n6000\n\n7000\n8000\n9000\n\n10000\n";
+
+    #[test]
+    fn crlf_example_gives_the_same_calories() {
+        let crlf = EXAMPLE.replace('\n', "\r\n");
+
+        let calories = parse_elf_calories(&normalize(&crlf));
+
+        assert_eq!(calories, parse_elf_calories(EXAMPLE));
+        assert_eq!(calories, [24000, 11000, 10000, 6000, 4000]);
+    }
 }
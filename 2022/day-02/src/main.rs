@@ -1,21 +1,27 @@
-/// Read the input file into a string and iterate through the lines.
+use aoc_common::{input_filename, print_answers, read_input};
+
+/// Iterate through the lines of the input.
 /// Map each line to a tuple of two characters representing the round shapes.
-fn get_rounds(filename: &str) -> Vec<(char, char)> {
-    std::fs::read_to_string(filename)
-        .unwrap()
+fn parse_rounds(input: &str) -> Vec<(char, char)> {
+    input
         .lines()
         .map(|round_line| {
             let chars = round_line.split(" ").collect::<Vec<_>>();
-            let get_char = |char: &str| char.chars().collect::<Vec<_>>().get(0).unwrap().to_owned();
+            let get_char = |char: &str| char.chars().next().unwrap();
 
             (
-                get_char(chars.get(0).unwrap()),
+                get_char(chars.first().unwrap()),
                 get_char(chars.get(1).unwrap()),
             )
         })
         .collect()
 }
 
+/// Read the input file into a string and parse the rounds from it.
+fn get_rounds(filename: &str) -> Vec<(char, char)> {
+    parse_rounds(&read_input(filename).unwrap())
+}
+
 /// Get the round score by comparing the combinations of symbols.
 /// For using Rock - 1 point, Paper - 2 points, Scissors - 3 points.
 /// For losing - 0 points, drawing - 3 points, winning - 6 points.
@@ -74,16 +80,36 @@ fn calculate_round_score_v2((opponent, you): &(char, char)) -> u32 {
     }
 }
 
+/// Calculate the total score by mapping over all the rounds, reading the second symbol as the
+/// shape to use, and summing the results.
+fn part1(rounds: &[(char, char)]) -> u32 {
+    rounds.iter().map(calculate_round_score).sum()
+}
+
+/// Calculate the total score by mapping over all the rounds, reading the second symbol as the
+/// outcome of the round, and summing the results.
+fn part2(rounds: &[(char, char)]) -> u32 {
+    rounds.iter().map(calculate_round_score_v2).sum()
+}
+
 fn main() {
     // Get the rounds in a vector.
-    let rounds = get_rounds("input.txt");
+    let rounds = get_rounds(input_filename());
+
+    print_answers(2, part1(&rounds), part2(&rounds));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Calculate the total score by mapping over all the rounds and summing the results.
-    let total_score = rounds.iter().map(calculate_round_score).sum::<u32>();
+    const EXAMPLE: &str = "A Y\nB X\nC Z\n";
 
-    // Calculate the total score by mapping over all the rounds and summing the results.
-    let total_score_v2 = rounds.iter().map(calculate_round_score_v2).sum::<u32>();
+    #[test]
+    fn example_scores() {
+        let rounds = parse_rounds(EXAMPLE);
 
-    println!("{total_score}");
-    println!("{total_score_v2}");
+        assert_eq!(part1(&rounds), 15);
+        assert_eq!(part2(&rounds), 12);
+    }
 }
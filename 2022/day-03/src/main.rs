@@ -1,92 +1,146 @@
-use std::collections::HashSet;
-
-/// Create a sum of sets.
-fn add_sets<'a, T>(first: &'a HashSet<T>, second: &'a HashSet<T>) -> HashSet<&'a T>
-where
-    T: Eq,
-    T: core::hash::Hash,
-{
-    HashSet::<&T>::from_iter(first.iter().chain(second.iter()))
+use aoc_common::{input_filename, parse_flag, print_answers, read_input};
+
+/// Calculate priority based on the character passed to the function.
+fn get_priority(item: &char) -> usize {
+    match item {
+        'a'..='z' => *item as usize - 'a' as usize + 1,
+        'A'..='Z' => *item as usize - 'A' as usize + 27,
+        _ => panic!("Invalid item: {item}"),
+    }
+}
+
+/// Encode the items of a string as a bit mask where the bit at each item's priority is set.
+/// Priorities go from 1 to 52, so they all fit into a `u64`.
+fn line_mask(items: &str) -> u64 {
+    items
+        .chars()
+        .fold(0, |mask, item| mask | 1 << get_priority(&item))
+}
+
+/// Get the priority of the lowest priority item in a bit mask of items, or `None` if the mask has
+/// no items.
+fn lowest_priority(mask: u64) -> Option<usize> {
+    (mask != 0).then(|| mask.trailing_zeros() as usize)
 }
 
-/// Read input file into string and iterate through the lines of input.
-/// Map each line into two hash sets made from halfs of the string at
-/// that line.
-fn get_rucksack_compartments(filename: &str) -> Vec<(HashSet<char>, HashSet<char>)> {
-    std::fs::read_to_string(filename)
-        .unwrap()
+/// Iterate through the lines of input. Map each line into two item masks
+/// made from halfs of the string at that line.
+fn parse_rucksack_compartments(input: &str) -> Vec<(u64, u64)> {
+    input
         .lines()
         .map(|line| {
             let (first_compartment, second_compartment) = line.split_at(line.len() / 2);
 
-            (
-                HashSet::from_iter(first_compartment.chars()),
-                HashSet::from_iter(second_compartment.chars()),
-            )
+            (line_mask(first_compartment), line_mask(second_compartment))
         })
         .collect()
 }
 
-/// Find the common item between two hash sets.
-fn find_common_item(
-    (first_compartment, second_compartment): &(HashSet<char>, HashSet<char>),
-) -> char {
-    let intersection = first_compartment
-        .intersection(&second_compartment)
-        .next()
-        .unwrap();
+/// Read input file into string and parse the rucksack compartments from it.
+fn get_rucksack_compartments(filename: &str) -> Vec<(u64, u64)> {
+    parse_rucksack_compartments(&read_input(filename).unwrap())
+}
 
-    intersection.to_owned()
+/// Find the priority of the common item between two compartments.
+fn find_common_item((first_compartment, second_compartment): &(u64, u64)) -> usize {
+    lowest_priority(first_compartment & second_compartment).unwrap()
 }
 
-/// Iterate through rucksacks 3 rucksacks at a time. Create hash sets
-/// from each rucksack and find the intersection between the 3 different
-/// sets and return it into a vector of characters.
-fn get_elf_groups(rucksacks: &[(HashSet<char>, HashSet<char>)]) -> Vec<char> {
+/// Iterate through rucksacks `group_size` rucksacks at a time. Combine
+/// the compartments of each rucksack and find the item common to every
+/// rucksack of a group, returning the priorities of those items.
+/// Returns an error if the rucksacks can't be split into whole groups,
+/// or if a group has no item in common.
+fn get_elf_groups(rucksacks: &[(u64, u64)], group_size: usize) -> Result<Vec<usize>, String> {
+    if group_size == 0 || !rucksacks.len().is_multiple_of(group_size) {
+        return Err(format!(
+            "Can't split {} rucksacks into groups of {group_size}",
+            rucksacks.len()
+        ));
+    }
+
     rucksacks
-        .iter()
+        .chunks(group_size)
         .enumerate()
-        .step_by(3)
-        .map(|(index, (left, right))| {
-            let first = add_sets(left, right);
-            let second = rucksacks.get(index + 1).unwrap();
-            let second = add_sets(&second.0, &second.1);
-            let third = rucksacks.get(index + 2).unwrap();
-            let third = add_sets(&third.0, &third.1);
-
-            let intersection = first
-                .intersection(&second)
-                .map(|char| char.to_owned())
-                .collect::<HashSet<&char>>();
-
-            let intersection = intersection.intersection(&third).next().unwrap();
-
-            intersection.to_owned().to_owned()
+        .map(|(index, group)| {
+            let common = group
+                .iter()
+                .fold(u64::MAX, |common, (left, right)| common & (left | right));
+
+            lowest_priority(common)
+                .ok_or_else(|| format!("Group {} has no item in common", index + 1))
         })
         .collect()
 }
 
-/// Calculate priority based on the character passed to the function.
-fn get_priority(item: &char) -> usize {
-    let mut range = ('a'..='z').collect::<String>();
-    range.extend('A'..='Z');
-
-    range.find(|char| &char == item).unwrap() + 1
-}
-
 fn main() {
     // Get the rucksacks from the input file.
-    let rucksacks = get_rucksack_compartments("input.txt");
+    let rucksacks = get_rucksack_compartments(input_filename());
 
     // Calculate the sum of priorities of the missplaced items in each rucksack.
-    let sum_of_priorites: usize = rucksacks
-        .iter()
-        .map(|rucksack| get_priority(&find_common_item(rucksack)))
-        .sum();
+    let sum_of_priorites: usize = rucksacks.iter().map(find_common_item).sum();
+
+    // The elves are split into groups of 3, unless a different group size is requested.
+    // Calculate the sum of priorities of the group badges for each group.
+    let sum_of_groups: usize = match parse_flag("--group-size", 3)
+        .and_then(|group_size| get_elf_groups(&rucksacks, group_size))
+    {
+        Ok(badges) => badges.iter().sum(),
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+
+    print_answers(3, sum_of_priorites, sum_of_groups);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "vJrwpWtwJgWrhcsFMMfFFhFp
+jqHRNqRjqzjGDLGLrsFMfFZSrLrFZsSL
+PmmdzqPrVvPwwTWBwg
+wMqvLMZHhHMvwLHjbvcjnnSBnvTQFn
+ttgJtRGJQctTZtZT
+CrZsJsPPZsGzwwsLwLmpwMDw
+";
+
+    #[test]
+    fn group_size_splits_the_example() {
+        let rucksacks = parse_rucksack_compartments(EXAMPLE);
+
+        let badges = get_elf_groups(&rucksacks, 3).unwrap();
+        assert_eq!(badges.iter().sum::<usize>(), 70);
+
+        // Every rucksack is a group of its own, so its badge is any item in it.
+        assert_eq!(get_elf_groups(&rucksacks, 1).unwrap().len(), 6);
+
+        assert_eq!(
+            get_elf_groups(&rucksacks, 4),
+            Err("Can't split 6 rucksacks into groups of 4".to_string())
+        );
+        assert_eq!(
+            get_elf_groups(&rucksacks[..5], 3),
+            Err("Can't split 5 rucksacks into groups of 3".to_string())
+        );
+        assert_eq!(
+            get_elf_groups(&rucksacks, 0),
+            Err("Can't split 6 rucksacks into groups of 0".to_string())
+        );
+    }
+
+    #[test]
+    fn masks_give_the_example_priorities() {
+        assert_eq!(line_mask("aZ"), (1 << 1) | (1 << 52));
+        assert_eq!(lowest_priority(0), None);
 
-    // Calculate the sum of priorities of the group badges for each 3-elf group.
-    let sum_of_groups: usize = get_elf_groups(&rucksacks).iter().map(get_priority).sum();
+        let rucksacks = parse_rucksack_compartments(EXAMPLE);
+        let sum_of_priorities = rucksacks.iter().map(find_common_item).sum::<usize>();
+        let badges = get_elf_groups(&rucksacks, 3).unwrap();
 
-    println!("{sum_of_priorites}");
-    println!("{sum_of_groups}");
+        assert_eq!(sum_of_priorities, 157);
+        assert_eq!(badges, [18, 52]);
+    }
 }
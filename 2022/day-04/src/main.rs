@@ -1,50 +1,88 @@
+use aoc_common::{extra_output, input_filename, print_answers, read_input};
+
+/// A section assignment - an inclusive range of section IDs.
+type Assignment = (u32, u32);
+
+/// Statistics about the overlapping sections of all the pairs.
+#[derive(Debug)]
+struct OverlapStats {
+    total_overlap_cells: u64,
+    max_overlap: Option<(usize, u32)>,
+}
+
 /// Parse the range from a string in the following format X-Y.
-fn get_range(range_str: &str) -> (u32, u32) {
+fn get_range(range_str: &str) -> Assignment {
     let ends = range_str.split("-").collect::<Vec<_>>();
-    let lower = ends.get(0).unwrap().parse().unwrap();
+    let lower = ends.first().unwrap().parse().unwrap();
     let upper = ends.get(1).unwrap().parse().unwrap();
 
     (lower, upper)
 }
 
 /// Parse the ranges from a string in the following format A-B,X-Y.
-fn get_range_pairs(range_pair_str: &str) -> ((u32, u32), (u32, u32)) {
+fn get_range_pairs(range_pair_str: &str) -> (Assignment, Assignment) {
     let ranges = range_pair_str.split(",").collect::<Vec<_>>();
-    let first = get_range(ranges.get(0).unwrap());
+    let first = get_range(ranges.first().unwrap());
     let second = get_range(ranges.get(1).unwrap());
 
     (first, second)
 }
 
 /// Check if one range fully containes another.
-fn some_fully_contained((first, second): &((u32, u32), (u32, u32))) -> bool {
-    if first.0 <= second.0 && first.1 >= second.1 {
-        true
-    } else if second.0 <= first.0 && second.1 >= first.1 {
-        true
-    } else {
-        false
-    }
+fn some_fully_contained((first, second): &(Assignment, Assignment)) -> bool {
+    (first.0 <= second.0 && first.1 >= second.1) || (second.0 <= first.0 && second.1 >= first.1)
 }
 
 /// Check if two ranges have an intersection.
-fn some_overlap((first, second): &((u32, u32), (u32, u32))) -> bool {
-    if first.0 <= second.0 && first.1 >= second.0 {
-        true
-    } else if first.0 <= second.1 && first.1 >= second.1 {
-        true
-    } else if second.0 <= first.0 && second.1 >= first.0 {
-        true
-    } else if second.0 <= first.1 && second.1 >= first.1 {
-        true
+fn some_overlap((first, second): &(Assignment, Assignment)) -> bool {
+    (first.0 <= second.0 && first.1 >= second.0)
+        || (first.0 <= second.1 && first.1 >= second.1)
+        || (second.0 <= first.0 && second.1 >= first.0)
+        || (second.0 <= first.1 && second.1 >= first.1)
+}
+
+/// Get the range of sections both assignments cover, if there is one.
+fn overlap_range(first: &Assignment, second: &Assignment) -> Option<Assignment> {
+    let lower = first.0.max(second.0);
+    let upper = first.1.min(second.1);
+
+    if lower <= upper {
+        Some((lower, upper))
     } else {
-        false
+        None
+    }
+}
+
+/// Calculate the total number of overlapping sections and find the pair with the most
+/// overlapping sections. The pair index starts at 0.
+fn overlap_stats(pairs: &[(Assignment, Assignment)]) -> OverlapStats {
+    let mut stats = OverlapStats {
+        total_overlap_cells: 0,
+        max_overlap: None,
+    };
+
+    for (index, (first, second)) in pairs.iter().enumerate() {
+        let Some((lower, upper)) = overlap_range(first, second) else {
+            continue;
+        };
+        let size = upper - lower + 1;
+
+        stats.total_overlap_cells += size as u64;
+
+        if stats
+            .max_overlap
+            .is_none_or(|(_, max_size)| size > max_size)
+        {
+            stats.max_overlap = Some((index, size));
+        }
     }
+
+    stats
 }
 
 /// Read lines from input file.
 fn read_range_pairs(filename: &str) -> Vec<String> {
-    std::fs::read_to_string(filename)
+    read_input(filename)
         .unwrap()
         .lines()
         .map(|line| line.to_string())
@@ -53,22 +91,64 @@ fn read_range_pairs(filename: &str) -> Vec<String> {
 
 fn main() {
     // Read range pairs from input file.
-    let range_pairs = read_range_pairs("input.txt");
+    let range_pairs = read_range_pairs(input_filename());
 
     // Get the count of pairs where one range fully containes another.
     let count_containing = range_pairs
         .iter()
-        .map(|range_pair_str| get_range_pairs(&range_pair_str))
-        .filter(|range_pair| some_fully_contained(&range_pair))
+        .map(|range_pair_str| get_range_pairs(range_pair_str))
+        .filter(some_fully_contained)
         .count();
 
     // Get the count of pairs with an intersection.
     let count_overlapping = range_pairs
         .iter()
-        .map(|range_pair_str| get_range_pairs(&range_pair_str))
-        .filter(|range_pair| some_overlap(&range_pair))
+        .map(|range_pair_str| get_range_pairs(range_pair_str))
+        .filter(some_overlap)
         .count();
 
-    println!("{count_containing}");
-    println!("{count_overlapping}");
+    // Print the overlap statistics if requested.
+    if extra_output("--stats") {
+        let pairs = range_pairs
+            .iter()
+            .map(|range_pair_str| get_range_pairs(range_pair_str))
+            .collect::<Vec<_>>();
+        let stats = overlap_stats(&pairs);
+
+        println!("Total overlapping sections: {}", stats.total_overlap_cells);
+
+        if let Some((index, size)) = stats.max_overlap {
+            println!("Largest overlap: pair {} with {size} sections", index + 1);
+        }
+
+        println!();
+    }
+
+    print_answers(4, count_containing, count_overlapping);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: [&str; 6] = [
+        "2-4,6-8", "2-3,4-5", "5-7,7-9", "2-8,3-7", "6-6,4-6", "2-6,4-8",
+    ];
+
+    #[test]
+    fn example_overlap_stats() {
+        let pairs = EXAMPLE.map(get_range_pairs);
+
+        let sizes = pairs
+            .iter()
+            .map(|(first, second)| {
+                overlap_range(first, second).map(|(lower, upper)| upper - lower + 1)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, [None, None, Some(1), Some(5), Some(1), Some(3)]);
+
+        let stats = overlap_stats(&pairs);
+        assert_eq!(stats.total_overlap_cells, 10);
+        assert_eq!(stats.max_overlap, Some((3, 5)));
+    }
 }
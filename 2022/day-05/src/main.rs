@@ -1,31 +1,34 @@
-/// Read the stacks and steps strings from the input file.
-fn get_stacks_and_steps(filename: &str) -> (String, String) {
-    let stacks_and_steps = std::fs::read_to_string(filename)
-        .unwrap()
+use aoc_common::{extra_output, input_filename, print_answers, read_input};
+
+/// Split the input into the stacks and steps strings, which are separated by an empty line.
+fn split_stacks_and_steps(input: &str) -> (String, String) {
+    let stacks_and_steps = input
         .split("\n\n")
         .map(|str| str.to_string())
         .collect::<Vec<_>>();
 
     (
-        stacks_and_steps.get(0).unwrap().to_owned(),
+        stacks_and_steps.first().unwrap().to_owned(),
         stacks_and_steps.get(1).unwrap().to_owned(),
     )
 }
 
+/// Read the stacks and steps strings from the input file.
+fn get_stacks_and_steps(filename: &str) -> (String, String) {
+    split_stacks_and_steps(&read_input(filename).unwrap())
+}
+
 /// Read an individual step from a line.
 /// Separate the line into words and only use the words which
 /// parse into numbers.
 fn read_step(step: &str) -> (u32, u32, u32) {
     let numbers = step
         .split(" ")
-        .filter_map(|word| match word.parse::<u32>() {
-            Ok(number) => Some(number),
-            Err(_) => None,
-        })
+        .filter_map(|word| word.parse::<u32>().ok())
         .collect::<Vec<_>>();
 
     (
-        numbers.get(0).unwrap().to_owned(),
+        numbers.first().unwrap().to_owned(),
         numbers.get(1).unwrap().to_owned() - 1,
         numbers.get(2).unwrap().to_owned() - 1,
     )
@@ -55,7 +58,7 @@ fn count_stacks(stacks: &str) -> usize {
 ///      is add the following character to the stack at the index of the
 ///      current skip.
 fn read_stacks(stacks_str: &str) -> Vec<Vec<char>> {
-    let number_of_stacks = count_stacks(&stacks_str.lines().last().unwrap());
+    let number_of_stacks = count_stacks(stacks_str.lines().last().unwrap());
 
     let mut stack_levels = stacks_str
         .lines()
@@ -86,10 +89,40 @@ fn read_stacks(stacks_str: &str) -> Vec<Vec<char>> {
     stacks
 }
 
+/// Render the stacks back into the crate diagram, the inverse of `read_stacks`.
+/// Draw the levels from the top down, leaving a gap for stacks which don't reach
+/// the current level, and finish with the numbering line.
+fn render_stacks(stacks: &[Vec<char>]) -> String {
+    let levels = stacks.iter().map(|stack| stack.len()).max().unwrap_or(0);
+
+    let mut lines = (0..levels)
+        .rev()
+        .map(|level| {
+            stacks
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(crate_name) => format!("[{crate_name}]"),
+                    None => "   ".to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>();
+
+    lines.push(
+        (1..=stacks.len())
+            .map(|number| format!(" {number} "))
+            .collect::<Vec<_>>()
+            .join(" "),
+    );
+
+    lines.join("\n")
+}
+
 /// Perform one step in the crane movement by iterating `crates` number of times
 /// to pop one crate from the stack at index `from_stack` and pushing it on
 /// top of the stack at index `to_stack`.
-fn perform_step(stacks: &mut Vec<Vec<char>>, &(crates, from_stack, to_stack): &(u32, u32, u32)) {
+fn perform_step(stacks: &mut [Vec<char>], &(crates, from_stack, to_stack): &(u32, u32, u32)) {
     for _ in 0..crates {
         let from_stack = stacks.get_mut(from_stack as usize).unwrap();
         let crate_to_transfer = from_stack.pop().unwrap();
@@ -104,7 +137,7 @@ fn perform_step(stacks: &mut Vec<Vec<char>>, &(crates, from_stack, to_stack): &(
 /// collecting `crates` number of crates from the stack at index
 /// `from_stack` and extending the stack at the index `to_stack` with
 /// those crates.
-fn perform_step_v2(stacks: &mut Vec<Vec<char>>, &(crates, from_stack, to_stack): &(u32, u32, u32)) {
+fn perform_step_v2(stacks: &mut [Vec<char>], &(crates, from_stack, to_stack): &(u32, u32, u32)) {
     let from_stack = stacks.get_mut(from_stack as usize).unwrap();
     let mut crates_to_transfer = Vec::with_capacity(crates as usize);
 
@@ -117,6 +150,44 @@ fn perform_step_v2(stacks: &mut Vec<Vec<char>>, &(crates, from_stack, to_stack):
     to_stack.extend_from_slice(&crates_to_transfer);
 }
 
+/// The crane models which differ in the way they move multiple crates.
+#[derive(Debug, Clone, Copy)]
+enum CraneModel {
+    /// Moves crates one at a time.
+    CrateMover9000,
+    /// Moves multiple crates at once, keeping their order.
+    CrateMover9001,
+}
+
+impl CraneModel {
+    /// Perform one step in the crane movement using this crane model.
+    fn perform_step(&self, stacks: &mut [Vec<char>], step: &(u32, u32, u32)) {
+        match self {
+            CraneModel::CrateMover9000 => perform_step(stacks, step),
+            CraneModel::CrateMover9001 => perform_step_v2(stacks, step),
+        }
+    }
+}
+
+/// Perform all the steps with the given crane model and record the state of
+/// the stacks after each step, starting with the initial state.
+fn run_with_history(
+    stacks: Vec<Vec<char>>,
+    steps: &[(u32, u32, u32)],
+    model: CraneModel,
+) -> Vec<Vec<Vec<char>>> {
+    let mut history = Vec::with_capacity(steps.len() + 1);
+    history.push(stacks);
+
+    for step in steps {
+        let mut stacks = history.last().unwrap().clone();
+        model.perform_step(&mut stacks, step);
+        history.push(stacks);
+    }
+
+    history
+}
+
 /// Collect the top crates from each stack into a String.
 fn get_top_crates(stacks: &[Vec<char>]) -> String {
     String::from_iter(stacks.iter().map(|stack| stack.last().unwrap()))
@@ -124,12 +195,13 @@ fn get_top_crates(stacks: &[Vec<char>]) -> String {
 
 fn main() {
     // Get stack and steps strings.
-    let (stacks_str, steps_str) = get_stacks_and_steps("input.txt");
+    let (stacks_str, steps_str) = get_stacks_and_steps(input_filename());
 
     // Get the stacks from the stacks string.
     let mut stacks = read_stacks(&stacks_str);
-    // Clone the stacks to use in part 2.
+    // Clone the stacks to use in part 2 and for the history.
     let mut stacks_v2 = stacks.clone();
+    let initial_stacks = stacks.clone();
 
     // Get the steps from the steps string.
     let steps = steps_str.lines().map(read_step).collect::<Vec<_>>();
@@ -137,7 +209,7 @@ fn main() {
     // Perform the steps for part 1.
     steps
         .iter()
-        .for_each(|step| perform_step(&mut stacks, step));
+        .for_each(|step| CraneModel::CrateMover9000.perform_step(&mut stacks, step));
 
     // Collect the top crates.
     let top_crates = get_top_crates(&stacks);
@@ -145,11 +217,98 @@ fn main() {
     // Perform the steps for part 2.
     steps
         .iter()
-        .for_each(|step| perform_step_v2(&mut stacks_v2, step));
+        .for_each(|step| CraneModel::CrateMover9001.perform_step(&mut stacks_v2, step));
 
     // Collect the top crates.
     let top_crates_v2 = get_top_crates(&stacks_v2);
 
-    println!("{top_crates}");
-    println!("{top_crates_v2}");
+    print_answers(5, top_crates, top_crates_v2);
+
+    // Print the state of the stacks after each step if requested.
+    if extra_output("--history") {
+        for (step, stacks) in run_with_history(initial_stacks, &steps, CraneModel::CrateMover9001)
+            .iter()
+            .enumerate()
+        {
+            println!("\nAfter step {step}:\n{}", render_stacks(stacks));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aoc_common::normalize;
+
+    const EXAMPLE: &str = "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n\nmove 1 from 2 to 1\nmove 3 from 1 to 3\nmove 2 from 2 to 1\nmove 1 from 1 to 2\n";
+
+    /// Get the top crates after moving the crates of the input with the given crane model.
+    fn top_crates(input: &str, model: CraneModel) -> String {
+        let (stacks_str, steps_str) = split_stacks_and_steps(input);
+        let mut stacks = read_stacks(&stacks_str);
+
+        for step in steps_str.lines().map(read_step) {
+            model.perform_step(&mut stacks, &step);
+        }
+
+        get_top_crates(&stacks)
+    }
+
+    #[test]
+    fn crlf_example_gives_the_same_top_crates() {
+        let crlf = normalize(&EXAMPLE.replace('\n', "\r\n"));
+
+        assert_eq!(top_crates(&crlf, CraneModel::CrateMover9000), "CMZ");
+        assert_eq!(top_crates(&crlf, CraneModel::CrateMover9001), "MCD");
+        assert_eq!(
+            top_crates(&crlf, CraneModel::CrateMover9000),
+            top_crates(EXAMPLE, CraneModel::CrateMover9000)
+        );
+    }
+
+    #[test]
+    fn history_has_a_snapshot_per_step() {
+        let (stacks_str, steps_str) = split_stacks_and_steps(EXAMPLE);
+        let stacks = read_stacks(&stacks_str);
+        let steps = steps_str.lines().map(read_step).collect::<Vec<_>>();
+
+        for (model, top_crates) in [
+            (CraneModel::CrateMover9000, "CMZ"),
+            (CraneModel::CrateMover9001, "MCD"),
+        ] {
+            let history = run_with_history(stacks.clone(), &steps, model);
+
+            assert_eq!(history.len(), steps.len() + 1);
+            assert_eq!(history[0], stacks);
+            assert_eq!(get_top_crates(history.last().unwrap()), top_crates);
+        }
+    }
+
+    #[test]
+    fn render_stacks_round_trips() {
+        let stacks = vec![vec!['Z', 'N'], vec!['M']];
+        assert_eq!(render_stacks(&stacks), "[N]    \n[Z] [M]\n 1   2 ");
+        assert_eq!(read_stacks(&render_stacks(&stacks)), stacks);
+
+        // Generate stacks of pseudo-random heights and crates with a linear congruential
+        // generator, so the test is the same on every run.
+        let mut seed = 12_345_u32;
+        let mut next = |bound: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) % bound
+        };
+
+        for _ in 0..20 {
+            let stacks = (0..1 + next(9))
+                .map(|_| {
+                    (0..next(6))
+                        .map(|_| (b'A' + next(26) as u8) as char)
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(read_stacks(&render_stacks(&stacks)), stacks);
+        }
+    }
 }
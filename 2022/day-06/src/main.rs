@@ -1,8 +1,10 @@
 use std::collections::HashSet;
 
+use aoc_common::{extra_output, input_filename, print_answers, read_input};
+
 /// Read the buffer from the input string.
 fn get_buffer(filename: &str) -> String {
-    std::fs::read_to_string(filename).unwrap()
+    read_input(filename).unwrap()
 }
 
 /// Check to see if sequence of characters is unique -
@@ -11,44 +13,75 @@ fn check_if_unique(marker: &str) -> bool {
     HashSet::<char>::from_iter(marker.chars()).len() == marker.len()
 }
 
-/// Find the start of the packet by searching for the first
-/// unique 4 character sequence.
-fn find_start_of_packet(buffer: &str) -> usize {
-    for index in 4..=buffer.len() {
-        let marker = buffer.get(index - 4..index).unwrap();
-
-        if check_if_unique(marker) {
-            return index;
-        }
-    }
+/// Find the first marker - the first unique sequence of `window` characters - and return the
+/// index of its end along with the marker itself. Returns None if there is no such sequence,
+/// which includes buffers shorter than the window.
+fn first_marker_window(buffer: &str, window: usize) -> Option<(usize, &str)> {
+    (window..=buffer.len()).find_map(|index| {
+        buffer
+            .get(index - window..index)
+            .filter(|marker| check_if_unique(marker))
+            .map(|marker| (index, marker))
+    })
+}
 
-    buffer.len() - 1
+/// Find the end of the first marker - the first unique sequence of `window` characters. Returns
+/// None if there is no such sequence, which includes buffers shorter than the window.
+fn find_marker(buffer: &str, window: usize) -> Option<usize> {
+    first_marker_window(buffer, window).map(|(index, _)| index)
 }
 
-/// Find the start of the message by searching for the first
-/// unique 14 character sequence.
-fn find_start_of_message(buffer: &str) -> usize {
-    for index in 14..=buffer.len() {
-        let marker = buffer.get(index - 14..index).unwrap();
+fn main() {
+    // Get the buffer from the input file.
+    let buffer = get_buffer(input_filename());
 
-        if check_if_unique(marker) {
-            return index;
+    // Print the start of packet and start of message markers if requested.
+    if extra_output("--markers") {
+        for (name, window) in [("packet", 4), ("message", 14)] {
+            if let Some((index, marker)) = first_marker_window(&buffer, window) {
+                println!("Start of {name} marker {marker} ends at {index}");
+            }
         }
+
+        println!();
     }
 
-    buffer.len() - 1
+    // Find the start of the packet - the first unique 4 character sequence - and the start of the
+    // message - the first unique 14 character sequence.
+    let (start_of_packet, start_of_message) =
+        match (find_marker(&buffer, 4), find_marker(&buffer, 14)) {
+            (Some(start_of_packet), Some(start_of_message)) => (start_of_packet, start_of_message),
+            _ => {
+                eprintln!("The buffer doesn't contain a start of packet and message marker");
+                std::process::exit(1);
+            }
+        };
+
+    print_answers(6, start_of_packet, start_of_message);
 }
 
-fn main() {
-    // Get the buffer from the input file.
-    let buffer = get_buffer("input.txt");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Find the start of the packet.
-    let start_of_packet = find_start_of_packet(&buffer);
+    #[test]
+    fn short_buffers_have_no_marker() {
+        assert_eq!(find_marker("abc", 4), None);
+        assert_eq!(find_marker("", 4), None);
+        assert_eq!(find_marker("abcd", 4), Some(4));
+        assert_eq!(find_marker("aaaa", 4), None);
+        assert_eq!(find_marker("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 14), Some(19));
+    }
 
-    // Find the start of the message.
-    let start_of_message = find_start_of_message(&buffer);
+    #[test]
+    fn first_marker_window_returns_the_marker() {
+        let buffer = "mjqjpqmgbljsphdztnvjfqwrcgsmlb";
 
-    println!("{start_of_packet}");
-    println!("{start_of_message}");
+        assert_eq!(first_marker_window(buffer, 4), Some((7, "jpqm")));
+        assert_eq!(
+            first_marker_window(buffer, 14),
+            Some((19, "qmgbljsphdztnv"))
+        );
+        assert_eq!(first_marker_window("abc", 4), None);
+    }
 }
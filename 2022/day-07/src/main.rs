@@ -1,6 +1,16 @@
 use std::collections::BTreeMap;
 
-/// Read commands from input file.
+use aoc_common::{extra_output, input_filename, print_answers, read_input};
+
+/// Statistics about the shape of the directory tree.
+#[derive(Debug)]
+struct TreeStats {
+    max_depth: usize,
+    total_files: usize,
+    total_dirs: usize,
+}
+
+/// Parse the commands from the input.
 /// Split the text input by `cd` command calls because we want to know
 /// when we change directory one level up or into a child directory.
 /// If we are changing one level up, than we pop the last directory
@@ -8,9 +18,8 @@ use std::collections::BTreeMap;
 /// the entry containing the child directory change will also have
 /// the `ls` command call after which we will have the list of files
 /// and directories contained inside which we will collect into a vector.
-fn read_commands_and_lists(filename: &str) -> BTreeMap<String, Vec<String>> {
-    let file = std::fs::read_to_string(filename).unwrap();
-    let line_iterator = file.split("$ cd ");
+fn parse_commands_and_lists(input: &str) -> BTreeMap<String, Vec<String>> {
+    let line_iterator = input.split("$ cd ");
 
     let mut current_context = vec![];
 
@@ -35,6 +44,11 @@ fn read_commands_and_lists(filename: &str) -> BTreeMap<String, Vec<String>> {
     }))
 }
 
+/// Read commands from input file.
+fn read_commands_and_lists(filename: &str) -> BTreeMap<String, Vec<String>> {
+    parse_commands_and_lists(&read_input(filename).unwrap())
+}
+
 /// Recursively get the size of a directory by summing the size of all
 /// the files it directly containes and the file sizes of any files
 /// contained within child directories by calling the same function.
@@ -53,34 +67,78 @@ fn get_dir_size(dir: &str, map: &BTreeMap<String, Vec<String>>) -> u32 {
 /// the `get_dir_size` function for each entry.
 fn get_dir_with_entries(map: &BTreeMap<String, Vec<String>>) -> BTreeMap<String, u32> {
     BTreeMap::from_iter(
-        map.iter()
-            .map(|(dir, _entries)| (dir.to_owned(), get_dir_size(&dir, map))),
+        map.keys()
+            .map(|dir| (dir.to_owned(), get_dir_size(dir, map))),
     )
 }
 
+/// Collect statistics about the directory tree by counting the listed directories
+/// and files. The depth of a directory is the number of directories above it, so
+/// the root directory has a depth of 0.
+fn tree_stats(map: &BTreeMap<String, Vec<String>>) -> TreeStats {
+    let max_depth = map
+        .keys()
+        .map(|dir| dir.matches('/').count() - 1)
+        .max()
+        .unwrap_or(0);
+
+    let total_files = map
+        .values()
+        .flatten()
+        .filter(|entry| !entry.starts_with("dir "))
+        .count();
+
+    TreeStats {
+        max_depth,
+        total_files,
+        total_dirs: map.len(),
+    }
+}
+
 fn main() {
     // Get the directories and their entries.
-    let map = read_commands_and_lists("input.txt");
+    let map = read_commands_and_lists(input_filename());
+
+    // Print the directory tree statistics if requested.
+    if extra_output("--stats") {
+        let stats = tree_stats(&map);
+
+        println!("Directories: {}", stats.total_dirs);
+        println!("Files: {}", stats.total_files);
+        println!("Max depth: {}\n", stats.max_depth);
+    }
 
     // Get the directory sizes.
     let sizes = get_dir_with_entries(&map);
 
     // Get the sum of all directories which have a size less than 100_000.
-    let sum = sizes
-        .iter()
-        .filter_map(|(_, size)| if size <= &100_000 { Some(size) } else { None })
-        .sum::<u32>();
+    let sum = sizes.values().filter(|size| **size <= 100_000).sum::<u32>();
 
     // Calculate how much needs to be deleted to have room for the update.
     let to_delete = 30_000_000 - (70_000_000 - sizes.get("/").unwrap());
 
     // Find the smallest of the directories large enough to free up enough space.
     let smallest_large_enough = sizes
-        .iter()
-        .filter_map(|(_, size)| if size > &&to_delete { Some(size) } else { None })
+        .values()
+        .filter(|size| **size > to_delete)
         .min()
         .unwrap();
 
-    println!("{sum}");
-    println!("{smallest_large_enough}");
+    print_answers(7, sum, smallest_large_enough);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = include_str!("../sample.txt");
+
+    #[test]
+    fn example_tree_stats() {
+        let stats = tree_stats(&parse_commands_and_lists(EXAMPLE));
+
+        assert_eq!(stats.total_dirs, 4);
+        assert_eq!(stats.total_files, 10);
+        assert_eq!(stats.max_depth, 2);
+    }
 }
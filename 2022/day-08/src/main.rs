@@ -1,181 +1,437 @@
+use std::ops::ControlFlow;
+
+use aoc_common::{
+    answers_only, extra_output, flag_value, input_filename, parse_grid, print_answers, read_input,
+    transpose,
+};
+
+/// Parse the tree height grid from the input.
+fn parse_trees(input: &str) -> Result<Vec<Vec<u8>>, String> {
+    let (grid, _) = parse_grid(input, |_, _, character| {
+        character
+            .to_digit(10)
+            .map(|digit| digit as u8)
+            .ok_or_else(|| format!("Invalid tree height: {character}"))
+    })?;
+
+    grid.into_iter()
+        .map(|row| row.into_iter().collect())
+        .collect()
+}
+
 /// Read tree height grid from input file.
-fn read_grid(filename: &str) -> Vec<Vec<u8>> {
-    std::fs::read_to_string(filename)
-        .unwrap()
-        .lines()
-        .map(|line| {
-            line.chars()
-                .map(|char| char.to_string().parse::<u8>().unwrap())
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<Vec<_>>>()
+fn read_grid(filename: &str) -> Result<Vec<Vec<u8>>, String> {
+    parse_trees(&read_input(filename)?)
 }
 
-/// Check if tree at position [`x`, `y`] is visible in
-/// grid `grid`.
-/// Iterate through trees left of the position,
-/// then iterate through trees right of the position,
-/// after that through trees above the position and
-/// finally through trees below the position.
-/// If at any direction we get to the end of the grid
-/// without seeing a tree as tall or taller than the
-/// tree at position [`x`, `y`] we return `true`.
-fn is_visible(x: usize, y: usize, grid: &[Vec<u8>]) -> bool {
-    let row = grid.get(y).unwrap();
-    let height = row.get(x).unwrap();
-
-    for index in 0..x {
-        let current_height = row.get(index).unwrap();
-
-        if index == x - 1 && current_height < height {
-            return true;
-        } else if current_height >= height {
-            break;
-        }
+/// An enum that represents the direction we look in from a tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dir {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+impl Dir {
+    /// List out all the directions in the order left, right, up and down.
+    pub fn all() -> [Self; 4] {
+        [Self::Left, Self::Right, Self::Up, Self::Down]
     }
+}
 
-    for index in (x + 1..row.len()).rev() {
-        let current_height = row.get(index).unwrap();
+/// Fold over the heights of the trees between the tree at position [`x`, `y`] and the edge in the
+/// given direction, starting with the nearest one, without collecting them first. The fold stops
+/// as soon as `f` breaks. Looking up or down sweeps the tree's row in `columns`, the transposed
+/// grid.
+fn directional_scan<T, F>(
+    x: usize,
+    y: usize,
+    grid: &[Vec<u8>],
+    columns: &[Vec<u8>],
+    dir: Dir,
+    init: T,
+    mut f: F,
+) -> T
+where
+    F: FnMut(T, u8) -> ControlFlow<T, T>,
+{
+    let (line, index) = match dir {
+        Dir::Left | Dir::Right => (&grid[y], x),
+        Dir::Up | Dir::Down => (&columns[x], y),
+    };
 
-        if index == x + 1 && current_height < height {
-            return true;
-        } else if current_height >= height {
-            break;
-        }
+    let result = match dir {
+        Dir::Left | Dir::Up => line[..index]
+            .iter()
+            .rev()
+            .try_fold(init, |state, height| f(state, *height)),
+        Dir::Right | Dir::Down => line[index + 1..]
+            .iter()
+            .try_fold(init, |state, height| f(state, *height)),
+    };
+
+    match result {
+        ControlFlow::Continue(state) | ControlFlow::Break(state) => state,
     }
+}
 
-    for index in 0..y {
-        let current_height = grid.get(index).unwrap().get(x).unwrap();
+/// Get the viewing distance from the tree at position [`x`, `y`] in the given direction - the
+/// number of trees up to and including the first tree as tall or taller than it, or up to the edge
+/// if there is no such tree.
+fn line_of_sight(x: usize, y: usize, grid: &[Vec<u8>], columns: &[Vec<u8>], dir: Dir) -> usize {
+    let height = grid[y][x];
 
-        if index == y - 1 && current_height < height {
-            return true;
-        } else if current_height >= height {
-            break;
+    directional_scan(x, y, grid, columns, dir, 0, |distance, current_height| {
+        if current_height >= height {
+            ControlFlow::Break(distance + 1)
+        } else {
+            ControlFlow::Continue(distance + 1)
         }
-    }
+    })
+}
 
-    for index in (y + 1..grid.len()).rev() {
-        let current_height = grid.get(index).unwrap().get(x).unwrap();
+/// Check if the view from the tree at position [`x`, `y`] to the edge in the given direction is
+/// blocked by a tree as tall or taller than it.
+fn blocked(x: usize, y: usize, grid: &[Vec<u8>], columns: &[Vec<u8>], dir: Dir) -> bool {
+    let height = grid[y][x];
 
-        if index == y + 1 && current_height < height {
-            return true;
-        } else if current_height >= height {
-            break;
+    directional_scan(x, y, grid, columns, dir, false, |_, current_height| {
+        if current_height >= height {
+            ControlFlow::Break(true)
+        } else {
+            ControlFlow::Continue(false)
         }
-    }
+    })
+}
 
-    false
+/// Find the edges tree at position [`x`, `y`] is visible from
+/// in grid `grid`, in the order left, right, up and down.
+/// A tree is visible from an edge if all trees between it
+/// and that edge are shorter than it, so trees on an edge
+/// are always visible from that edge.
+fn visibility_directions(x: usize, y: usize, grid: &[Vec<u8>], columns: &[Vec<u8>]) -> [bool; 4] {
+    Dir::all().map(|dir| !blocked(x, y, grid, columns, dir))
+}
+
+/// Check if tree at position [`x`, `y`] is visible in
+/// grid `grid` from at least one edge.
+fn is_visible(x: usize, y: usize, grid: &[Vec<u8>], columns: &[Vec<u8>]) -> bool {
+    visibility_directions(x, y, grid, columns).contains(&true)
+}
+
+/// Render a heatmap of the grid where every tree is replaced by
+/// the number of edges it is visible from.
+fn render_visibility(grid: &[Vec<u8>], columns: &[Vec<u8>]) -> String {
+    grid.iter()
+        .enumerate()
+        .map(|(y, row)| {
+            (0..row.len())
+                .map(|x| {
+                    let directions = visibility_directions(x, y, grid, columns);
+                    let count = directions.iter().filter(|visible| **visible).count();
+
+                    char::from_digit(count as u32, 10).unwrap()
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Get the viewing distances from the tree at position [`x`, `y`]
+/// in the order left, right, up and down.
+fn viewing_distances(x: usize, y: usize, grid: &[Vec<u8>], columns: &[Vec<u8>]) -> [usize; 4] {
+    Dir::all().map(|dir| line_of_sight(x, y, grid, columns, dir))
 }
 
 /// Calculate the scenic score for tree at position [`x`, `y`]
-/// by iterating through trees from the curren tree towards an
-/// edge. If at any time we encounter a tree as tall or taller
-/// than the tree at position [`x`, `y`] we multiply the scenic
-/// score by the distance between the trees. If we get to an
-/// edge we multiply the scenic score by the distance from the
-/// edge to the tree we are calculating the score for.
-fn scenic_score(x: usize, y: usize, grid: &[Vec<u8>]) -> usize {
-    let row = grid.get(y).unwrap();
-    let height = row.get(x).unwrap();
-    let mut scenic_score = 1;
-
-    for index in (0..x).rev() {
-        let current_height = row.get(index).unwrap();
-
-        if index == 0 {
-            scenic_score *= x;
-        } else if current_height >= height {
-            scenic_score *= x - index;
-            break;
-        }
+/// by multiplying the viewing distances in each direction.
+/// Trees on an edge have a viewing distance of 0 towards that
+/// edge, so their scenic score is 0.
+fn scenic_score(x: usize, y: usize, grid: &[Vec<u8>], columns: &[Vec<u8>]) -> usize {
+    viewing_distances(x, y, grid, columns).iter().product()
+}
+
+/// Get the position of the tree to show the viewing distances of from the `--view X,Y` flag.
+/// The flag is ignored if only the answers should be printed.
+fn get_view_position(grid: &[Vec<u8>]) -> Result<Option<(usize, usize)>, String> {
+    let Some(value) = flag_value("--view") else {
+        return Ok(None);
+    };
+
+    if answers_only() {
+        return Ok(None);
     }
 
-    for index in x + 1..row.len() {
-        let current_height = row.get(index).unwrap();
+    let value = value.ok_or("Missing position after --view")?;
+    let (x, y) = value
+        .split_once(',')
+        .and_then(|(x, y)| Some((x.parse::<usize>().ok()?, y.parse::<usize>().ok()?)))
+        .ok_or_else(|| format!("Invalid position \"{value}\", expected X,Y"))?;
 
-        if index == row.len() - 1 {
-            scenic_score *= row.len() - 1 - x;
-        } else if current_height >= height {
-            scenic_score *= index - x;
-            break;
-        }
+    if grid.get(y).and_then(|row| row.get(x)).is_none() {
+        return Err(format!("Position {x},{y} is outside of the grid"));
     }
 
-    for index in (0..y).rev() {
-        let current_height = grid.get(index).unwrap().get(x).unwrap();
+    Ok(Some((x, y)))
+}
+
+/// Count the trees visible from at least one edge.
+fn count_visible(grid: &[Vec<u8>], columns: &[Vec<u8>]) -> usize {
+    grid.iter()
+        .enumerate()
+        .map(|(y, row)| {
+            (0..row.len())
+                .filter(|x| is_visible(*x, y, grid, columns))
+                .count()
+        })
+        .sum()
+}
+
+/// Find the max scenic score within the grid. Trees on an edge score 0, so they never beat an
+/// interior tree.
+fn max_scenic_score(grid: &[Vec<u8>], columns: &[Vec<u8>]) -> usize {
+    grid.iter()
+        .enumerate()
+        .flat_map(|(y, row)| (0..row.len()).map(move |x| (x, y)))
+        .map(|(x, y)| scenic_score(x, y, grid, columns))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Count the visible trees by walking from every tree to each edge cell by cell. This is the slow
+/// reference `--validate-answers` checks the answer to the first part against.
+fn part1_reference(grid: &[Vec<u8>]) -> usize {
+    let mut count = 0;
+
+    for y in 0..grid.len() {
+        for x in 0..grid[y].len() {
+            let height = grid[y][x];
+            let left = (0..x).all(|i| grid[y][i] < height);
+            let right = (x + 1..grid[y].len()).all(|i| grid[y][i] < height);
+            let up = (0..y).all(|j| grid[j][x] < height);
+            let down = (y + 1..grid.len()).all(|j| grid[j][x] < height);
 
-        if index == 0 {
-            scenic_score *= y;
-        } else if current_height >= height {
-            scenic_score *= y - index;
-            break;
+            if left || right || up || down {
+                count += 1;
+            }
         }
     }
 
-    for index in y + 1..grid.len() {
-        let current_height = grid.get(index).unwrap().get(x).unwrap();
+    count
+}
+
+/// Find the max scenic score by stepping from every tree towards each edge until the view is
+/// blocked. This is the slow reference `--validate-answers` checks the answer to the second part
+/// against.
+fn part2_reference(grid: &[Vec<u8>]) -> usize {
+    let mut max_score = 0;
+
+    for y in 0..grid.len() {
+        for x in 0..grid[y].len() {
+            let height = grid[y][x];
+            let mut score = 1;
+
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let (mut current_x, mut current_y) = (x as isize, y as isize);
+                let mut distance = 0;
+
+                loop {
+                    current_x += dx;
+                    current_y += dy;
+
+                    let Some(current_height) = grid
+                        .get(current_y as usize)
+                        .and_then(|row| row.get(current_x as usize))
+                    else {
+                        break;
+                    };
 
-        if index == grid.len() - 1 {
-            scenic_score *= grid.len() - 1 - y;
-        } else if current_height >= height {
-            scenic_score *= index - y;
-            break;
+                    distance += 1;
+
+                    if *current_height >= height {
+                        break;
+                    }
+                }
+
+                score *= distance;
+            }
+
+            max_score = max_score.max(score);
         }
     }
 
-    scenic_score
+    max_score
 }
 
 fn main() {
     // Get the grid from the input file.
-    let grid = read_grid("input.txt");
+    let grid = match read_grid(input_filename()) {
+        Ok(grid) => grid,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
 
-    // Count the visible trees inside the grid.
-    let visible_count_inside = grid
-        .iter()
-        .enumerate()
-        // We skip the first row as it is an edge and all trees are visible.
-        .skip(1)
-        // We ignore the last row for the same reason.
-        .take(grid.len() - 2)
-        .map(|(y, row)| {
-            row.iter()
-                .enumerate()
-                // We skip the first column as it is an edge and all trees are visible.
-                .skip(1)
-                // We ignore the last column for the same reason.
-                .take(grid.last().unwrap().len() - 2)
-                .filter(|(x, _)| is_visible(*x, y, &grid))
-                .count()
-        })
-        .sum::<usize>();
+    // Transpose the grid so that looking up or down walks along a row.
+    let columns = transpose(&grid);
+
+    // Render the visibility heatmap if requested.
+    if extra_output("--heatmap") {
+        println!("{}\n", render_visibility(&grid, &columns));
+    }
+
+    // Show the viewing distances from a single tree if requested.
+    match get_view_position(&grid) {
+        Ok(Some((x, y))) => {
+            let [left, right, up, down] = viewing_distances(x, y, &grid, &columns);
+
+            println!(
+                "Left: {left}, right: {right}, up: {up}, down: {down} (scenic score {})\n",
+                left * right * up * down
+            );
+        }
+        Ok(None) => {}
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    }
 
-    // We count the number of trees on the edges.
-    let visible_count_outside = grid.len() * 2 + grid.last().unwrap().len() * 2 - 4;
-    let visible_count = visible_count_inside + visible_count_outside;
+    // Count the trees visible from at least one edge.
+    let visible_count = count_visible(&grid, &columns);
 
     // Find the max scenic score within the grid.
-    let max_scenic_score = grid
-        .iter()
-        .enumerate()
-        // We skip the first row as it is an edge and scenic scores will be 0.
-        .skip(1)
-        // We ignore the last row for the same reason.
-        .take(grid.len() - 2)
-        .map(|(y, row)| {
-            row.iter()
-                .enumerate()
-                // We skip the first column as it is an edge and scenic scores will be 0.
-                .skip(1)
-                // We ignore the last column for the same reason.
-                .take(grid.len() - 2)
-                .map(|(x, _)| scenic_score(x, y, &grid))
-                .max()
-                .unwrap()
-        })
-        .max()
-        .unwrap();
+    let max_scenic_score = max_scenic_score(&grid, &columns);
+
+    // Check the answers against the slow reference implementations if requested.
+    if std::env::args().any(|arg| arg == "--validate-answers") {
+        let reference = (part1_reference(&grid), part2_reference(&grid));
+
+        if reference != (visible_count, max_scenic_score) {
+            eprintln!(
+                "Answers {visible_count} and {max_scenic_score} don't match the reference answers {} and {}",
+                reference.0, reference.1
+            );
+            std::process::exit(1);
+        }
+
+        if !answers_only() {
+            eprintln!("Answers match the reference implementation.");
+        }
+    }
+
+    print_answers(8, visible_count, max_scenic_score);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "30373\n25512\n65332\n33549\n35390\n";
+
+    #[test]
+    fn answers_match_the_reference() {
+        for input in [EXAMPLE, include_str!("../input.txt")] {
+            let grid = parse_trees(input).unwrap();
+            let columns = transpose(&grid);
+
+            assert_eq!(count_visible(&grid, &columns), part1_reference(&grid));
+            assert_eq!(max_scenic_score(&grid, &columns), part2_reference(&grid));
+        }
+
+        let grid = parse_trees(EXAMPLE).unwrap();
+        assert_eq!(count_visible(&grid, &transpose(&grid)), 21);
+        assert_eq!(max_scenic_score(&grid, &transpose(&grid)), 8);
+    }
+
+    #[test]
+    fn visibility_directions_of_edge_and_hidden_trees() {
+        let grid = parse_trees(EXAMPLE).unwrap();
+        let columns = transpose(&grid);
+
+        // The left edge tree in the second row is visible from the left edge.
+        assert!(visibility_directions(0, 1, &grid, &columns)[0]);
+        // The top right tree is visible from the top and right edges.
+        assert_eq!(
+            visibility_directions(4, 0, &grid, &columns),
+            [false, true, true, false]
+        );
+        // The tree in the middle is hidden from every edge.
+        assert_eq!(visibility_directions(2, 2, &grid, &columns), [false; 4]);
+        assert!(!is_visible(2, 2, &grid, &columns));
+    }
+
+    #[test]
+    fn directional_scan_starts_with_the_nearest_tree() {
+        let grid = parse_trees(EXAMPLE).unwrap();
+        let columns = transpose(&grid);
+        let collect = |dir| {
+            directional_scan(
+                2,
+                3,
+                &grid,
+                &columns,
+                dir,
+                Vec::new(),
+                |mut heights, height| {
+                    heights.push(height);
+                    ControlFlow::Continue(heights)
+                },
+            )
+        };
+
+        assert_eq!(collect(Dir::Left), [3, 3]);
+        assert_eq!(collect(Dir::Right), [4, 9]);
+        assert_eq!(collect(Dir::Up), [3, 5, 3]);
+        assert_eq!(collect(Dir::Down), [3]);
+    }
+
+    #[test]
+    fn line_of_sight_from_the_scored_tree() {
+        let grid = parse_trees(EXAMPLE).unwrap();
+        let columns = transpose(&grid);
 
-    println!("{visible_count}");
-    println!("{max_scenic_score}");
+        // The tree of height 5 in the middle of the fourth row has the best scenic score.
+        for (dir, distance, is_blocked) in [
+            (Dir::Left, 2, false),
+            (Dir::Right, 2, true),
+            (Dir::Up, 2, true),
+            (Dir::Down, 1, false),
+        ] {
+            assert_eq!(
+                line_of_sight(2, 3, &grid, &columns, dir),
+                distance,
+                "{dir:?}"
+            );
+            assert_eq!(blocked(2, 3, &grid, &columns, dir), is_blocked, "{dir:?}");
+        }
+    }
+
+    #[test]
+    fn edge_trees_score_zero() {
+        let grid = parse_trees(EXAMPLE).unwrap();
+        let columns = transpose(&grid);
+
+        for (x, y) in [(0, 0), (2, 0), (4, 2), (3, 4), (0, 3)] {
+            assert_eq!(scenic_score(x, y, &grid, &columns), 0, "tree at {x},{y}");
+        }
+
+        // The tree of height 5 in the middle of the second row sees 1, 2, 1 and 2 trees.
+        assert_eq!(scenic_score(2, 1, &grid, &columns), 4);
+    }
+
+    #[test]
+    fn viewing_distances_of_the_scored_tree() {
+        let grid = parse_trees(EXAMPLE).unwrap();
+        let columns = transpose(&grid);
+
+        let distances = viewing_distances(2, 3, &grid, &columns);
+
+        assert_eq!(distances, [2, 2, 2, 1]);
+        assert_eq!(distances.iter().product::<usize>(), 8);
+        assert_eq!(scenic_score(2, 3, &grid, &columns), 8);
+    }
 }
@@ -1,99 +1,410 @@
 use std::collections::HashSet;
 
-/// Read moves from the input file into direction and step count pairs.
-fn read_moves(filename: &str) -> Vec<(char, u32)> {
-    std::fs::read_to_string(filename)
-        .unwrap()
+use aoc_common::{
+    bounding_box, extra_output, input_filename, print_answers, read_input, report_error, verbose,
+    ParseError,
+};
+
+/// An enum that represents the direction the head of the rope moves in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dir {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl TryFrom<&str> for Dir {
+    type Error = String;
+
+    /// Parse a direction from its letter.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "U" => Ok(Self::Up),
+            "D" => Ok(Self::Down),
+            "L" => Ok(Self::Left),
+            "R" => Ok(Self::Right),
+            _ => Err(format!("Invalid direction \"{value}\"")),
+        }
+    }
+}
+
+/// A struct that represents a move of the head - a direction and the number of steps to take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Move {
+    dir: Dir,
+    steps: u32,
+}
+
+/// Parse the moves from the input. Returns an error naming the offending line if a line doesn't
+/// have a valid direction and step count.
+fn parse_moves(input: &str) -> Result<Vec<Move>, ParseError> {
+    input
         .lines()
-        .map(|line| {
-            let mut split = line.split(" ");
-            let direction = split.next().unwrap();
-            let steps = split.next().unwrap();
+        .enumerate()
+        .map(|(index, line)| {
+            let (direction, steps) = line
+                .split_once(" ")
+                .ok_or_else(|| ParseError::new(index + 1, "expected a direction and steps"))?;
 
-            (direction.parse().unwrap(), steps.parse().unwrap())
+            Ok(Move {
+                dir: Dir::try_from(direction).map_err(|error| ParseError::new(index + 1, error))?,
+                steps: steps.parse().map_err(|error| {
+                    ParseError::new(index + 1, format!("invalid steps: {error}"))
+                })?,
+            })
         })
         .collect()
 }
 
 /// Return next position of head based on the direction and
 /// current position.
-fn move_head(direction: &char, (y, x): &(i32, i32)) -> (i32, i32) {
+fn move_head(direction: &Dir, (y, x): &(i32, i32)) -> (i32, i32) {
     match direction {
-        'U' => (y + 1, *x),
-        'D' => (y - 1, *x),
-        'L' => (*y, x - 1),
-        'R' => (*y, x + 1),
-        _ => {
-            panic!("Invalid direction!");
+        Dir::Up => (y + 1, *x),
+        Dir::Down => (y - 1, *x),
+        Dir::Left => (*y, x - 1),
+        Dir::Right => (*y, x + 1),
+    }
+}
+
+/// Get the new position of a knot following the knot ahead of it. A knot which is still touching
+/// the knot ahead - overlapping it, or adjacent to it even diagonally - stays where it is.
+/// Otherwise it takes one step towards the knot ahead, diagonally if they aren't in the same row
+/// or column.
+fn follow(head: (i32, i32), tail: (i32, i32)) -> (i32, i32) {
+    let diff_y = head.0 - tail.0;
+    let diff_x = head.1 - tail.1;
+
+    if diff_y.abs() <= 1 && diff_x.abs() <= 1 {
+        return tail;
+    }
+
+    (tail.0 + diff_y.signum(), tail.1 + diff_x.signum())
+}
+
+/// Move the rope one step in the given direction.
+/// Move the head and then go through the knots and update the
+/// position based on the knot that preceeded.
+fn move_rope(direction: &Dir, rope: &mut [(i32, i32)]) {
+    // Get the head.
+    let head = rope.get_mut(0).unwrap();
+
+    // Move head.
+    *head = move_head(direction, head);
+
+    // Save current knot.
+    let mut current_knot = *head;
+
+    // Iterate through the tail to update positions. Skip the head.
+    for knot in rope.iter_mut().skip(1) {
+        let next_knot = follow(current_knot, *knot);
+
+        // If the knot didn't move, none of the knots after it will move either.
+        if next_knot == *knot {
+            break;
         }
+
+        *knot = next_knot;
+        current_knot = next_knot;
     }
 }
 
-/// Count the tail steps for a given set of moves and a given tail
-/// length.
+/// Find the positions visited by the tail for a given set of moves
+/// and a given tail length.
 /// Create a set of visited positions and insert the position of the
 /// tail at each step increment.
 /// Keep a vector of tail knot positions for each knot in the tail.
-/// Go through the knots and update the position based on the knot
-/// that preceeded.
-fn count_tail_steps(moves: &[(char, u32)], tail_length: usize) -> usize {
+fn visited_positions(moves: &[Move], tail_length: usize) -> HashSet<(i32, i32)> {
     let mut set = HashSet::new();
     set.insert((0, 0));
     let mut tail = Vec::from_iter((0..tail_length).map(|_| (0, 0)));
 
-    moves.iter().for_each(|(direction, steps)| {
+    moves.iter().for_each(|Move { dir, steps }| {
         for _ in 0..*steps {
-            // Get the head.
-            let head = tail.get_mut(0).unwrap();
-
-            // Move head.
-            *head = move_head(direction, &head);
-
-            // Save current knot.
-            let mut current_knot = head.clone();
-
-            // Iterate through the tail to update positions. Skip the head.
-            for knot in tail.iter_mut().skip(1) {
-                // Calculate the distance between two knots.
-                let diff_x = knot.1.abs_diff(current_knot.1);
-                let diff_y = knot.0.abs_diff(current_knot.0);
-
-                // Skip position update if the knots are still touching.
-                if diff_x + diff_y < 2 || (diff_x == 1 && diff_y == 1) {
-                    break;
-                }
-
-                // Update both coordinates if the knots are diagonally separated.
-                // Otherwise update only one coordinate.
-                if diff_x + diff_y > 2 {
-                    knot.1 += if knot.1 < current_knot.1 { 1 } else { -1 };
-                    knot.0 += if knot.0 < current_knot.0 { 1 } else { -1 };
-                } else if diff_x > 1 {
-                    knot.1 += if knot.1 < current_knot.1 { 1 } else { -1 };
-                } else {
-                    knot.0 += if knot.0 < current_knot.0 { 1 } else { -1 };
-                }
-
-                current_knot = knot.clone();
-            }
+            move_rope(dir, &mut tail);
 
             set.insert(*tail.last().unwrap());
         }
     });
 
-    set.len()
+    set
+}
+
+/// Count the distinct positions visited by the tail of a rope of each of the given lengths. The
+/// knots of a rope only follow the knots ahead of them, so a shorter rope moves exactly like the
+/// start of a longer one. Only the longest rope is simulated, tracking the positions visited by the
+/// last knot of each shorter rope along the way. Returns an error if a rope has no knots.
+fn simulate_ropes(moves: &[Move], lengths: &[usize]) -> Result<Vec<usize>, String> {
+    if lengths.contains(&0) {
+        return Err("A rope needs at least one knot".to_string());
+    }
+
+    let Some(longest) = lengths.iter().max() else {
+        return Ok(vec![]);
+    };
+
+    let mut rope = Vec::from_iter((0..*longest).map(|_| (0, 0)));
+    let mut visited = lengths
+        .iter()
+        .map(|_| HashSet::from([(0, 0)]))
+        .collect::<Vec<_>>();
+
+    moves.iter().for_each(|Move { dir, steps }| {
+        for _ in 0..*steps {
+            move_rope(dir, &mut rope);
+
+            for (length, set) in lengths.iter().zip(visited.iter_mut()) {
+                set.insert(rope[length - 1]);
+            }
+        }
+    });
+
+    Ok(visited.iter().map(|set| set.len()).collect())
+}
+
+/// Render one frame of the rope for each step increment.
+/// First replay the moves to record the knot positions after each step
+/// and find the bounding box of the whole run, including the start.
+/// Then draw every frame within that box with `H` for the head, the
+/// knot number for the rest of the knots and `s` for the start. Knots
+/// past the ninth don't have a digit, so they are drawn as `*`.
+fn step_frames(moves: &[Move], tail_length: usize) -> Vec<String> {
+    let mut tail = Vec::from_iter((0..tail_length).map(|_| (0, 0)));
+    let mut states = vec![];
+
+    moves.iter().for_each(|Move { dir, steps }| {
+        for _ in 0..*steps {
+            move_rope(dir, &mut tail);
+
+            states.push(tail.clone());
+        }
+    });
+
+    // Find the bounds of all the positions the knots have been in, including the start.
+    let ((min_y, min_x), (max_y, max_x)) = bounding_box(
+        states
+            .iter()
+            .flatten()
+            .chain([(0, 0)].iter())
+            .map(|(y, x)| (*y as i64, *x as i64)),
+    )
+    .unwrap();
+    let (min_y, min_x, max_y, max_x) = (min_y as i32, min_x as i32, max_y as i32, max_x as i32);
+
+    states
+        .iter()
+        .map(|knots| {
+            (min_y..=max_y)
+                .rev()
+                .map(|y| {
+                    (min_x..=max_x)
+                        .map(|x| {
+                            // Draw the knot closest to the head if several knots overlap.
+                            match knots.iter().position(|knot| knot == &(y, x)) {
+                                Some(0) => 'H',
+                                Some(index) => char::from_digit(index as u32, 10).unwrap_or('*'),
+                                None if (y, x) == (0, 0) => 's',
+                                None => '.',
+                            }
+                        })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect()
+}
+
+/// Render the positions visited by the tail with `#`, and the start
+/// with `s`. The grid covers the bounding box of the visited positions,
+/// which can stretch into negative coordinates, so the start isn't
+/// necessarily in a corner.
+fn render_visited(moves: &[Move], tail_length: usize) -> String {
+    let visited = visited_positions(moves, tail_length);
+
+    // The start is always visited, so there is always a bounding box.
+    let ((min_y, min_x), (max_y, max_x)) =
+        bounding_box(visited.iter().map(|(y, x)| (*y as i64, *x as i64))).unwrap();
+    let (min_y, min_x, max_y, max_x) = (min_y as i32, min_x as i32, max_y as i32, max_x as i32);
+
+    (min_y..=max_y)
+        .rev()
+        .map(|y| {
+            (min_x..=max_x)
+                .map(|x| match (y, x) {
+                    (0, 0) => 's',
+                    position if visited.contains(&position) => '#',
+                    _ => '.',
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 fn main() {
     // Get the moves list from the input file.
-    let moves = read_moves("input.txt");
+    let input = match read_input(input_filename()) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    let moves = match parse_moves(&input) {
+        Ok(moves) => moves,
+        Err(error) => {
+            eprintln!("{}", report_error(&error, &input, verbose()));
+            std::process::exit(1);
+        }
+    };
 
-    // Count the steps for a two knot rope.
-    let steps_count = count_tail_steps(&moves, 2);
+    // Count the steps for a two knot rope and a ten knot rope in a single pass.
+    let counts = match simulate_ropes(&moves, &[2, 10]) {
+        Ok(counts) => counts,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    let [steps_count, steps_tail_count] = counts[..] else {
+        unreachable!("Two rope lengths give two counts");
+    };
+
+    print_answers(9, steps_count, steps_tail_count);
 
-    // Count the steps for a ten knot rope.
-    let steps_tail_count = count_tail_steps(&moves, 10);
+    // Print the positions visited by the tail of the ten knot rope if requested.
+    if extra_output("--visited") {
+        println!("\n{}", render_visited(&moves, 10));
+    }
 
-    println!("{steps_count}");
-    println!("{steps_tail_count}");
+    // Print the state of the ten knot rope after each step if requested.
+    if extra_output("--frames") {
+        for frame in step_frames(&moves, 10) {
+            println!("\n{frame}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2\n";
+
+    #[test]
+    fn one_pass_counts_every_rope_length() {
+        let moves = parse_moves(EXAMPLE).unwrap();
+
+        assert_eq!(simulate_ropes(&moves, &[2, 10]), Ok(vec![13, 1]));
+        assert_eq!(
+            simulate_ropes(&moves, &[2]),
+            Ok(vec![visited_positions(&moves, 2).len()])
+        );
+        assert_eq!(
+            simulate_ropes(&moves, &[0, 10]),
+            Err("A rope needs at least one knot".to_string())
+        );
+    }
+
+    #[test]
+    fn one_frame_per_step_ending_at_the_tail() {
+        let moves = parse_moves(EXAMPLE).unwrap();
+        let total_steps = moves
+            .iter()
+            .map(|Move { steps, .. }| *steps as usize)
+            .sum::<usize>();
+
+        let frames = step_frames(&moves, 2);
+        assert_eq!(frames.len(), total_steps);
+
+        // Replay the moves to find the last position visited by the tail.
+        let mut rope = [(0, 0); 2];
+        for Move { dir, steps } in &moves {
+            for _ in 0..*steps {
+                move_rope(dir, &mut rope);
+            }
+        }
+        let (y, x) = rope[1];
+
+        // The example never leaves the box from 0,0 to 4,5, and the rows are drawn top down.
+        let last_frame = frames.last().unwrap().lines().collect::<Vec<_>>();
+        assert_eq!(last_frame.len(), 5);
+        assert_eq!(
+            last_frame[(4 - y) as usize].chars().nth(x as usize),
+            Some('1')
+        );
+    }
+
+    #[test]
+    fn knots_past_the_ninth_are_drawn_as_stars() {
+        let moves = parse_moves("R 12\n").unwrap();
+
+        let last_frame = step_frames(&moves, 12).pop().unwrap();
+        assert_eq!(last_frame, "s**987654321H");
+    }
+
+    #[test]
+    fn visited_render_covers_negative_coordinates() {
+        let moves = parse_moves("L 3\nD 3\n").unwrap();
+        let render = render_visited(&moves, 2);
+
+        assert_eq!(render, ".##s\n#...\n#...");
+        assert_ne!(render.chars().next(), Some('s'));
+    }
+
+    #[test]
+    fn moves_parse_or_name_the_bad_line() {
+        assert_eq!(
+            parse_moves("R 4\n"),
+            Ok(vec![Move {
+                dir: Dir::Right,
+                steps: 4
+            }])
+        );
+        assert_eq!(
+            parse_moves("R 4\nX 3\n"),
+            Err(ParseError::new(2, "Invalid direction \"X\""))
+        );
+        assert!(parse_moves("R four\n")
+            .unwrap_err()
+            .to_string()
+            .starts_with("Line 1: invalid steps"));
+    }
+
+    #[test]
+    fn follow_moves_only_when_not_touching() {
+        let tail = (0, 0);
+
+        // A tail touching the head stays where it is.
+        for head in [
+            (-1, -1),
+            (-1, 0),
+            (-1, 1),
+            (0, -1),
+            (0, 0),
+            (0, 1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+        ] {
+            assert_eq!(follow(head, tail), tail, "head at {head:?}");
+        }
+
+        // A tail two apart takes a step towards the head, diagonally if needed.
+        for (head, expected) in [
+            ((2, 0), (1, 0)),
+            ((-2, 0), (-1, 0)),
+            ((0, 2), (0, 1)),
+            ((0, -2), (0, -1)),
+            ((2, 1), (1, 1)),
+            ((1, -2), (1, -1)),
+            ((-2, -1), (-1, -1)),
+            ((2, 2), (1, 1)),
+            ((-2, 2), (-1, 1)),
+        ] {
+            assert_eq!(follow(head, tail), expected, "head at {head:?}");
+        }
+    }
 }
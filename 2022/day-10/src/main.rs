@@ -1,58 +1,259 @@
-/// Read the instructions from the input file and
-/// calculate the value of the `X` register for each cycle.
-fn get_cycles(filename: &str) -> Vec<i32> {
-    let mut cycles = vec![1];
+use std::collections::HashSet;
+use std::str::FromStr;
 
-    std::fs::read_to_string(filename)
-        .unwrap()
+use aoc_common::{
+    answers_only, extra_output, flag_value, input_filename, print_answers, read_input,
+    report_error, verbose, ParseError,
+};
+
+/// An instruction of the CPU program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Instruction {
+    /// Take one cycle and do nothing.
+    Noop,
+    /// Take two cycles and then add the value to the `X` register.
+    Addx(i32),
+}
+
+impl FromStr for Instruction {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        match line.split_once(' ') {
+            None if line == "noop" => Ok(Self::Noop),
+            Some(("addx", value)) => value
+                .parse()
+                .map(Self::Addx)
+                .map_err(|_| format!("Invalid addx value: {value}")),
+            _ => Err(format!("Invalid instruction: {line}")),
+        }
+    }
+}
+
+/// Parse the program from the input. Returns an error with the line number of the first
+/// instruction that couldn't be parsed.
+fn parse_program(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    input
         .lines()
-        .for_each(|line| {
-            let x = cycles.last().unwrap().clone();
-            cycles.push(x);
+        .enumerate()
+        .map(|(index, line)| {
+            line.parse()
+                .map_err(|error| ParseError::new(index + 1, error))
+        })
+        .collect()
+}
 
-            if line.starts_with("addx") {
-                let v = line.get(5..).unwrap().parse::<i32>().unwrap();
-                cycles.push(x + v);
-            }
-        });
+/// Execute the program and yield the cycle number, starting at 1, along with the value of the `X`
+/// register during that cycle. An `addx` takes two cycles and only changes the register after the
+/// second one, so both of its cycles see the old value.
+fn execute(program: &[Instruction]) -> impl Iterator<Item = (usize, i32)> + '_ {
+    program
+        .iter()
+        .scan(1, |x, instruction| {
+            let during = *x;
 
+            Some(match instruction {
+                Instruction::Noop => vec![during],
+                Instruction::Addx(value) => {
+                    *x += value;
+                    vec![during, during]
+                }
+            })
+        })
+        .flatten()
+        .enumerate()
+        .map(|(index, x)| (index + 1, x))
+}
+
+/// Find the (column, row) coordinates of every lit pixel on the 40x6 CRT screen from the values of
+/// the `X` register during each cycle. The pixel drawn during a cycle is lit if the sprite,
+/// centered on the `X` register value, covers its column.
+fn lit_pixels(cycles: &[i32]) -> HashSet<(usize, usize)> {
     cycles
+        .iter()
+        .take(40 * 6)
+        .enumerate()
+        .map(|(index, x)| (index % 40, index / 40, x))
+        .filter(|(column, _, x)| (*column as i32).abs_diff(**x) < 2)
+        .map(|(column, row, _)| (column, row))
+        .collect()
+}
+
+/// Render the CRT screen from the set of lit pixels.
+fn render_screen(pixels: &HashSet<(usize, usize)>) -> String {
+    (0..6)
+        .map(|row| {
+            (0..40)
+                .map(|column| {
+                    if pixels.contains(&(column, row)) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Compare a rendered CRT screen to the expected one. Returns `None` if they are equal, or the
+/// rendered screen with every differing pixel replaced by `X`, followed by the row and column of
+/// each differing pixel, both starting at 0.
+fn diff_crt(got: &str, expected: &str) -> Option<String> {
+    if got.trim_end() == expected.trim_end() {
+        return None;
+    }
+
+    let got_rows = got.lines().collect::<Vec<_>>();
+    let expected_rows = expected.lines().collect::<Vec<_>>();
+    let mut marked = vec![];
+    let mut differences = vec![];
+
+    for row in 0..got_rows.len().max(expected_rows.len()) {
+        let got_row = got_rows
+            .get(row)
+            .copied()
+            .unwrap_or("")
+            .chars()
+            .collect::<Vec<_>>();
+        let expected_row = expected_rows
+            .get(row)
+            .copied()
+            .unwrap_or("")
+            .chars()
+            .collect::<Vec<_>>();
+
+        let marked_row = (0..got_row.len().max(expected_row.len()))
+            .map(|column| {
+                let got_pixel = got_row.get(column).copied().unwrap_or(' ');
+                let expected_pixel = expected_row.get(column).copied().unwrap_or(' ');
+
+                if got_pixel == expected_pixel {
+                    got_pixel
+                } else {
+                    differences.push(format!(
+                        "Row {row}, column {column}: expected '{expected_pixel}', got '{got_pixel}'"
+                    ));
+                    'X'
+                }
+            })
+            .collect::<String>();
+
+        marked.push(marked_row);
+    }
+
+    Some(format!(
+        "{}\n\n{}",
+        marked.join("\n"),
+        differences.join("\n")
+    ))
 }
 
 fn main() {
-    // Get the cycles from the input file.
-    let cycles = get_cycles("input.txt");
+    // Get the program from the input file.
+    let input = match read_input(input_filename()) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    let program = match parse_program(&input) {
+        Ok(program) => program,
+        Err(error) => {
+            eprintln!("{}", report_error(&error, &input, verbose()));
+            std::process::exit(1);
+        }
+    };
+
+    // Print the value of the `X` register during each cycle if requested.
+    if extra_output("--cycles") {
+        for (cycle, x) in execute(&program) {
+            println!("Cycle {cycle}: X = {x}");
+        }
+
+        println!();
+    }
 
     // Calculate the sum of the products of the cycle number and `X` register
     // value at each 40 cycles starting from the 20th cycle.
-    let sum = cycles
-        .iter()
-        .enumerate()
-        .skip(19)
-        .step_by(40)
-        .map(|(cycle, x)| (cycle + 1) as i32 * x)
+    let sum = execute(&program)
+        .filter(|(cycle, _)| cycle % 40 == 20)
+        .map(|(cycle, x)| cycle as i32 * x)
         .sum::<i32>();
 
-    // Print the CRT screen into a String by iterating over each cycle.
-    let crt_screen =
-        cycles
-            .iter()
-            .enumerate()
-            .skip(1)
-            .fold("".to_string(), |screen, (cycle, x)| {
-                // Check to see if the middle pixel of the sprite is visible at current position.
-                let pixel = if (cycle as i32 % 40).abs_diff(*x) < 2 {
-                    "#"
-                } else {
-                    "."
-                };
+    // Get the value of the `X` register during each cycle.
+    let cycles = execute(&program).map(|(_, x)| x).collect::<Vec<_>>();
+
+    // Draw the CRT screen into a String from the lit pixels.
+    let crt_screen = render_screen(&lit_pixels(&cycles));
+
+    // Compare the CRT screen to the expected image if requested, by default `expected_crt.txt`.
+    if let Some(filename) = flag_value("--diff-render") {
+        let filename = filename.unwrap_or_else(|| "expected_crt.txt".to_string());
+        let expected = match read_input(&filename) {
+            Ok(expected) => expected,
+            Err(error) => {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+        };
+
+        if let Some(diff) = diff_crt(&crt_screen, &expected) {
+            eprintln!("The CRT screen doesn't match {filename}:\n{diff}");
+            std::process::exit(1);
+        }
+
+        if !answers_only() {
+            eprintln!("The CRT screen matches {filename}.");
+        }
+    }
+
+    print_answers(10, sum, crt_screen);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lit_pixels_match_the_expected_screen() {
+        let program = parse_program(include_str!("../input.txt")).unwrap();
+        let cycles = execute(&program).map(|(_, x)| x).collect::<Vec<_>>();
+        let expected = include_str!("../expected_crt.txt");
+
+        let pixels = lit_pixels(&cycles);
+
+        assert_eq!(pixels.len(), expected.matches('#').count());
+        assert_eq!(render_screen(&pixels), expected.trim_end());
+    }
+
+    #[test]
+    fn execute_yields_the_value_during_each_cycle() {
+        let program = parse_program("noop\naddx 3\naddx -5\n").unwrap();
+
+        assert_eq!(
+            execute(&program).collect::<Vec<_>>(),
+            [(1, 1), (2, 1), (3, 1), (4, 4), (5, 4)]
+        );
+    }
+
+    #[test]
+    fn diff_crt_pinpoints_a_flipped_pixel() {
+        let expected = include_str!("../expected_crt.txt");
+        assert_eq!(diff_crt(expected.trim_end(), expected), None);
 
-                // Add new line if the current cycle is the last cycle in the row.
-                let new_line = if cycle % 40 == 0 { "\n" } else { "" };
+        // Flip the pixel in the third row and fifth column.
+        let mut rows = expected.lines().map(str::to_string).collect::<Vec<_>>();
+        rows[2].replace_range(4..5, "#");
+        let got = rows.join("\n");
 
-                format!("{screen}{pixel}{new_line}")
-            });
+        let diff = diff_crt(&got, expected).unwrap();
+        let (marked, differences) = diff.split_once("\n\n").unwrap();
 
-    println!("{sum}");
-    println!("{crt_screen}");
+        assert_eq!(marked.matches('X').count(), 1);
+        assert_eq!(marked.lines().nth(2).unwrap().find('X'), Some(4));
+        assert_eq!(differences, "Row 2, column 4: expected '.', got '#'");
+    }
 }
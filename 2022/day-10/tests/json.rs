@@ -0,0 +1,44 @@
+use std::process::Command;
+
+use serde_json::{json, Value};
+
+/// Run day-10 against the sample input with the given flags and parse its output as JSON.
+fn run_json(flags: &[&str]) -> Value {
+    let output = Command::new(env!("CARGO_BIN_EXE_day-10"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["--example", "--json"])
+        .args(flags)
+        .output()
+        .expect("Couldn't run day-10");
+
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 1);
+
+    serde_json::from_str(&stdout).expect("The output isn't valid JSON")
+}
+
+#[test]
+fn json_holds_both_answers() {
+    // The CRT screen of the example draws a pattern of growing runs of lit pixels.
+    let screen = [
+        "##..##..##..##..##..##..##..##..##..##..",
+        "###...###...###...###...###...###...###.",
+        "####....####....####....####....####....",
+        "#####.....#####.....#####.....#####.....",
+        "######......######......######......####",
+        "#######.......#######.......#######.....",
+    ]
+    .join("\n");
+
+    assert_eq!(
+        run_json(&[]),
+        json!({"day": 10, "part1": "13140", "part2": screen})
+    );
+}
+
+#[test]
+fn json_leaves_out_the_extra_output() {
+    assert_eq!(run_json(&["--cycles"]), run_json(&[]));
+}
@@ -1,3 +1,10 @@
+use std::str::FromStr;
+
+use aoc_common::{
+    extra_output, input_filename, parse_flag, print_answers, read_input, report_error, verbose,
+    ParseError,
+};
+
 /// A struct representing the worry operation from an item inspection of a monkey.
 #[derive(Debug, Clone, Copy)]
 enum Operation {
@@ -7,31 +14,28 @@ enum Operation {
 
 impl Operation {
     /// Creates a new operation from the operation string and the right hand operand.
-    pub fn new(op: &str, value: &str) -> Self {
+    pub fn new(op: &str, value: &str) -> Result<Self, String> {
         let value = match value.parse::<u128>() {
             Ok(int) => Some(int),
-            Err(_) => None,
+            Err(_) if value == "old" => None,
+            Err(_) => return Err(format!("Invalid operand: {value}")),
         };
 
         match op {
-            "+" => Operation::Add(value),
-            "*" => Operation::Multiply(value),
-            _ => panic!("Invalid operation!"),
+            "+" => Ok(Operation::Add(value)),
+            "*" => Ok(Operation::Multiply(value)),
+            _ => Err(format!("Invalid operation: {op}")),
         }
     }
 
-    /// Performs the worry operation on an item.
-    pub fn run_operation(&self, item: &u128) -> u128 {
+    /// Performs the worry operation on an item. Returns an error if the new worry level
+    /// doesn't fit into a `u128`.
+    pub fn run_operation(&self, item: &u128) -> Result<u128, String> {
         match *self {
-            Operation::Add(value) => match value {
-                Some(value) => value + item,
-                None => item + item,
-            },
-            Operation::Multiply(value) => match value {
-                Some(value) => value * item,
-                None => item * item,
-            },
+            Operation::Add(value) => item.checked_add(value.unwrap_or(*item)),
+            Operation::Multiply(value) => item.checked_mul(value.unwrap_or(*item)),
         }
+        .ok_or_else(|| format!("Worry level overflow running {self:?} on item {item}"))
     }
 }
 
@@ -47,58 +51,63 @@ struct Monkey {
     items_inspected: u128,
 }
 
+/// Parse the last word of the line at `index` of the monkey information.
+fn parse_last_word<T: FromStr>(monkey: &[&str], index: usize) -> Result<T, (usize, String)> {
+    let line = monkey
+        .get(index)
+        .ok_or((index, "Missing line".to_string()))?;
+
+    line.split(" ").last().unwrap().parse().map_err(|_| {
+        (
+            index,
+            format!("Expected a number at the end of \"{}\"", line.trim()),
+        )
+    })
+}
+
 impl Monkey {
     /// Creates a new monkey from the slice of monkey information - usually five consecutive lines.
-    pub fn new(monkey: &[&str]) -> Self {
+    /// Returns the index of the offending line along with the error if the information is invalid.
+    pub fn new(monkey: &[&str]) -> Result<Self, (usize, String)> {
         // Collect the item worry values from the monkey.
         let items = monkey
-            .get(0)
-            .unwrap()
+            .first()
+            .ok_or((0, "Missing line".to_string()))?
             .split(":")
             .last()
             .unwrap()
             .split(",")
-            .map(|item| item.trim().parse().unwrap())
-            .collect();
+            .map(|item| {
+                item.trim()
+                    .parse()
+                    .map_err(|_| (0, format!("Invalid item: {}", item.trim())))
+            })
+            .collect::<Result<_, _>>()?;
 
         // Collect the monkey operation.
-        let second_line = monkey.get(1).unwrap().split(" ").collect::<Vec<_>>();
+        let second_line = monkey
+            .get(1)
+            .ok_or((1, "Missing line".to_string()))?
+            .split(" ")
+            .collect::<Vec<_>>();
         let operation = Operation::new(
-            second_line.get(second_line.len() - 2).unwrap(),
+            second_line
+                .get(second_line.len().saturating_sub(2))
+                .unwrap(),
             second_line.last().unwrap(),
-        );
+        )
+        .map_err(|error| (1, error))?;
 
         // Get the divisor for decision making.
-        let divisor = monkey
-            .get(2)
-            .unwrap()
-            .split(" ")
-            .last()
-            .unwrap()
-            .parse()
-            .unwrap();
+        let divisor = parse_last_word(monkey, 2)?;
 
         // Get the index of the monkey to go to if the division is with modulo 0.
-        let true_index = monkey
-            .get(3)
-            .unwrap()
-            .split(" ")
-            .last()
-            .unwrap()
-            .parse()
-            .unwrap();
+        let true_index = parse_last_word(monkey, 3)?;
 
         // Get the index of the monkey to go to if the division is with modulo other than 0.
-        let false_index = monkey
-            .get(4)
-            .unwrap()
-            .split(" ")
-            .last()
-            .unwrap()
-            .parse()
-            .unwrap();
+        let false_index = parse_last_word(monkey, 4)?;
 
-        Self {
+        Ok(Self {
             items,
             operation,
             divisor,
@@ -106,27 +115,81 @@ impl Monkey {
             false_index,
             // Set the items inspected to start at 0.
             items_inspected: 0,
-        }
+        })
     }
 }
 
-/// Collect the monkeys from the input file.
-fn read_monkeys(filename: &str) -> Vec<Monkey> {
-    std::fs::read_to_string(filename)
-        .unwrap()
+/// Parse the monkeys from the input. Returns an error with the line number of the first line that
+/// couldn't be parsed, or that throws to a monkey which doesn't exist.
+fn parse_monkeys(input: &str) -> Result<Vec<Monkey>, ParseError> {
+    // Keep track of the line number the current monkey starts at.
+    let mut first_line = 1;
+
+    let monkeys = input
         .split("\n\n")
-        .map(|monkey_string| Monkey::new(&monkey_string.lines().skip(1).collect::<Vec<_>>()))
-        .collect()
+        .map(|monkey_string| {
+            let monkey = Monkey::new(&monkey_string.lines().skip(1).collect::<Vec<_>>())
+                .map(|monkey| (first_line, monkey))
+                .map_err(|(index, error)| ParseError::new(first_line + index + 1, error));
+
+            first_line += monkey_string.lines().count() + 1;
+
+            monkey
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Make sure the monkeys only throw to monkeys which exist, the throw targets are on the fifth
+    // and sixth line of each monkey.
+    for (first_line, monkey) in &monkeys {
+        for (offset, target) in [(4, monkey.true_index), (5, monkey.false_index)] {
+            if target >= monkeys.len() {
+                return Err(ParseError::new(
+                    first_line + offset,
+                    format!("There is no monkey {target} to throw to"),
+                ));
+            }
+        }
+    }
+
+    Ok(monkeys.into_iter().map(|(_, monkey)| monkey).collect())
+}
+
+/// The way worry levels are kept manageable after each inspection.
+enum WorryReduction {
+    /// Don't reduce the worry level at all. The worry levels quickly outgrow a `u128`, at which
+    /// point the operations return an error.
+    None,
+    /// Divide the worry level by three, as in the original rules.
+    DivideByThree,
+    /// Get the modulo of the worry level with a base divisor - the product of all the monkey
+    /// divisors, which keeps the divisibility tests intact.
+    Modulo(u128),
+}
+
+impl WorryReduction {
+    /// Reduce the worry level of an item.
+    pub fn apply(&self, worry: u128) -> u128 {
+        match self {
+            Self::None => worry,
+            Self::DivideByThree => worry / 3,
+            Self::Modulo(divisor) => worry % divisor,
+        }
+    }
 }
 
 /// Run a monkey turn by iterating through all the items of the monkey.
-fn run_monkey_turn(index: usize, monkeys: &mut [Monkey]) {
+/// Returns an error if any of the worry levels overflows.
+fn run_monkey_turn(
+    index: usize,
+    monkeys: &mut [Monkey],
+    reduction: &WorryReduction,
+) -> Result<(), String> {
     let mut monkey = monkeys.get(index).unwrap().clone();
 
-    monkey.items.iter().for_each(|item| {
-        let new_item_value = monkey.operation.run_operation(&item) / 3;
+    for item in monkey.items.iter() {
+        let new_item_value = reduction.apply(monkey.operation.run_operation(item)?);
 
-        let next_monkey_index = if new_item_value % monkey.divisor == 0 {
+        let next_monkey_index = if new_item_value.is_multiple_of(monkey.divisor) {
             monkey.true_index
         } else {
             monkey.false_index
@@ -136,98 +199,327 @@ fn run_monkey_turn(index: usize, monkeys: &mut [Monkey]) {
 
         destination_monkey.items.push(new_item_value);
         monkey.items_inspected += 1;
-    });
+    }
 
     monkey.items.clear();
 
     let new_monkey = monkeys.get_mut(index).unwrap();
 
     *new_monkey = monkey;
-}
 
-/// Run the monkey turn according to the new rules. Instead of dividing the worry level by three,
-/// get the modulo of the worry level with base divisor - this is the product of all divisors in
-/// the monkey slice.
-fn run_new_rules_turn(index: usize, monkeys: &mut [Monkey], divisor: &u128) {
-    let mut monkey = monkeys.get(index).unwrap().clone();
+    Ok(())
+}
 
-    monkey.items.iter().for_each(|item| {
-        let new_item_value = monkey.operation.run_operation(&item) % divisor;
+/// Run a monkey turn for each monkey in the slice.
+fn run_round(monkeys: &mut [Monkey], reduction: &WorryReduction) -> Result<(), String> {
+    for index in 0..monkeys.len() {
+        run_monkey_turn(index, monkeys, reduction)?;
+    }
 
-        let next_monkey_index = if new_item_value % monkey.divisor == 0 {
-            monkey.true_index
-        } else {
-            monkey.false_index
-        };
+    Ok(())
+}
 
-        let destination_monkey = monkeys.get_mut(next_monkey_index).unwrap();
+/// Run a number of rounds and collect the number of items each monkey has inspected after every
+/// round.
+fn run_rounds_collecting(
+    monkeys: &mut [Monkey],
+    rounds: usize,
+    reduction: &WorryReduction,
+) -> Result<Vec<Vec<u128>>, String> {
+    let mut inspections = Vec::with_capacity(rounds);
+
+    for round in 0..rounds {
+        run_round(monkeys, reduction).map_err(|error| format!("Round {}: {error}", round + 1))?;
+
+        inspections.push(
+            monkeys
+                .iter()
+                .map(|monkey| monkey.items_inspected)
+                .collect(),
+        );
+    }
 
-        destination_monkey.items.push(new_item_value);
-        monkey.items_inspected += 1;
-    });
+    Ok(inspections)
+}
 
-    monkey.items.clear();
+/// Calculate the monkey business value by taking the `top_k` most active monkeys and multiplying
+/// the number of items inspected.
+fn monkey_business(items_inspected: &[u128], top_k: usize) -> u128 {
+    let mut items_inspected = items_inspected.to_vec();
 
-    let new_monkey = monkeys.get_mut(index).unwrap();
+    // Sort the number of items inspected in descending order.
+    items_inspected.sort_by(|first, second| second.cmp(first));
 
-    *new_monkey = monkey;
+    items_inspected.iter().take(top_k).product()
 }
 
-/// Run a monkey turn for each monkey in the slice.
-fn run_round(monkeys: &mut [Monkey]) {
-    for index in 0..monkeys.len() {
-        run_monkey_turn(index, monkeys);
+/// Print the number of items each monkey inspected after the first round, the twentieth round
+/// and every thousandth round.
+fn print_inspections(inspections: &[Vec<u128>]) {
+    for (round, items_inspected) in inspections.iter().enumerate() {
+        let round = round + 1;
+
+        if round != 1 && round != 20 && round % 1_000 != 0 {
+            continue;
+        }
+
+        println!("== After round {round} ==");
+
+        for (index, count) in items_inspected.iter().enumerate() {
+            println!("Monkey {index} inspected items {count} times.");
+        }
+
+        println!();
     }
 }
 
-/// Run a monkey turn for each monkey in the slice according to the new rules.
-fn run_new_rules_round(monkeys: &mut [Monkey], divisor: &u128) {
-    for index in 0..monkeys.len() {
-        run_new_rules_turn(index, monkeys, divisor);
+/// Get the number of the most active monkeys to multiply the inspections of from the `--top N`
+/// flag, which defaults to 2.
+fn get_top_k() -> Result<usize, String> {
+    match parse_flag("--top", 2)? {
+        0 => Err("The --top flag expects at least one monkey".to_string()),
+        top_k => Ok(top_k),
     }
 }
 
 fn main() {
     // Get the monkeys into a vector.
-    let mut monkeys = read_monkeys("input.txt");
-    // Clone the monkeys for part two.
-    let mut monkeys_clone = monkeys.clone();
+    let input = match read_input(input_filename()) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    let monkeys = match parse_monkeys(&input) {
+        Ok(monkeys) => monkeys,
+        Err(error) => {
+            eprintln!("{}", report_error(&error, &input, verbose()));
+            std::process::exit(1);
+        }
+    };
 
-    // Run twenty rounds.
-    for _ in 0..20 {
-        run_round(&mut monkeys);
+    // Only check that the input is valid if requested.
+    if std::env::args().any(|arg| arg == "--validate") {
+        println!("ok, {} monkeys", monkeys.len());
+        return;
     }
 
-    // Sort the monkeys by number of items inspected in descending order.
-    monkeys.sort_by(|first, second| second.items_inspected.cmp(&first.items_inspected));
+    // Show what happens to the worry levels without any reduction if requested.
+    if extra_output("--no-reduction") {
+        match run_rounds_collecting(&mut monkeys.clone(), 10_000, &WorryReduction::None) {
+            Ok(_) => println!("No overflow in 10000 rounds without worry reduction"),
+            Err(error) => println!("{error}"),
+        }
 
-    // Calculate the monkey business value by taking the two most active monkeys and multiplying
-    // the number of items inspected.
-    let monkey_business = monkeys
-        .iter()
-        .take(2)
-        .fold(1, |product, monkey| product * monkey.items_inspected);
+        println!();
+    }
 
     // Calculate the divisor - the product of divisors for each monkey.
-    let divisor = monkeys_clone
+    let divisor = monkeys
         .iter()
         .fold(1, |product, monkey| product * monkey.divisor);
 
-    // Run ten thousand rounds.
-    for _ in 0..10_000 {
-        run_new_rules_round(&mut monkeys_clone, &divisor);
+    // Run twenty rounds, then ten thousand rounds with the new rules on a fresh set of monkeys.
+    // Stop if the worry levels get too big to be represented.
+    let runs = [
+        (20, WorryReduction::DivideByThree),
+        (10_000, WorryReduction::Modulo(divisor)),
+    ]
+    .map(|(rounds, reduction)| {
+        match run_rounds_collecting(&mut monkeys.clone(), rounds, &reduction) {
+            Ok(inspections) => inspections,
+            Err(error) => {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+        }
+    });
+
+    // Print the inspection counts per round if requested.
+    if extra_output("--inspections") {
+        runs.iter()
+            .for_each(|inspections| print_inspections(inspections));
     }
 
-    // Sort the monkeys by number of items inspected in descending order.
-    monkeys_clone.sort_by(|first, second| second.items_inspected.cmp(&first.items_inspected));
+    // Multiply the inspections of the two most active monkeys, unless a different number of
+    // monkeys is requested.
+    let top_k = match get_top_k() {
+        Ok(top_k) => top_k,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
 
-    // Calculate the monkey business value by taking the two most active monkeys and multiplying
-    // the number of items inspected.
-    let monkey_business_new = monkeys_clone
-        .iter()
-        .take(2)
-        .fold(1, |product, monkey| product * monkey.items_inspected);
+    let [monkey_business, monkey_business_new] =
+        runs.map(|inspections| monkey_business(inspections.last().unwrap(), top_k));
 
-    println!("{monkey_business}");
-    println!("{monkey_business_new}");
+    print_answers(11, monkey_business, monkey_business_new);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example from the puzzle description.
+    const EXAMPLE: &str = "Monkey 0:
+  Starting items: 79, 98
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 2
+    If false: throw to monkey 3
+
+Monkey 1:
+  Starting items: 54, 65, 75, 74
+  Operation: new = old + 6
+  Test: divisible by 19
+    If true: throw to monkey 2
+    If false: throw to monkey 0
+
+Monkey 2:
+  Starting items: 79, 60, 97
+  Operation: new = old * old
+  Test: divisible by 13
+    If true: throw to monkey 1
+    If false: throw to monkey 3
+
+Monkey 3:
+  Starting items: 74
+  Operation: new = old + 3
+  Test: divisible by 17
+    If true: throw to monkey 0
+    If false: throw to monkey 1";
+
+    /// The items each monkey of the example holds after the first round, as stated in the puzzle.
+    const EXAMPLE_ITEMS_AFTER_ROUND_1: [&[u128]; 4] = [
+        &[20, 23, 27, 26],
+        &[2080, 25, 167, 207, 401, 1046],
+        &[],
+        &[],
+    ];
+
+    /// The number of items each monkey of the example inspected after twenty rounds, as stated in
+    /// the puzzle.
+    const EXAMPLE_INSPECTIONS_AFTER_ROUND_20: [u128; 4] = [101, 95, 7, 105];
+
+    #[test]
+    fn example_replay_matches_the_puzzle() {
+        let mut monkeys = parse_monkeys(EXAMPLE).unwrap();
+
+        run_round(&mut monkeys, &WorryReduction::DivideByThree).unwrap();
+
+        let items = monkeys
+            .iter()
+            .map(|monkey| monkey.items.as_slice())
+            .collect::<Vec<_>>();
+        assert_eq!(items, EXAMPLE_ITEMS_AFTER_ROUND_1);
+
+        let inspections =
+            run_rounds_collecting(&mut monkeys, 19, &WorryReduction::DivideByThree).unwrap();
+
+        assert_eq!(
+            inspections.last().unwrap()[..],
+            EXAMPLE_INSPECTIONS_AFTER_ROUND_20
+        );
+    }
+
+    /// Get the product of the divisors of the monkeys.
+    fn base_divisor(monkeys: &[Monkey]) -> u128 {
+        monkeys.iter().map(|monkey| monkey.divisor).product()
+    }
+
+    #[test]
+    fn collects_the_published_inspection_counts() {
+        let mut monkeys = parse_monkeys(EXAMPLE).unwrap();
+        let reduction = WorryReduction::Modulo(base_divisor(&monkeys));
+
+        let inspections = run_rounds_collecting(&mut monkeys, 1_000, &reduction).unwrap();
+
+        assert_eq!(inspections.len(), 1_000);
+        assert_eq!(inspections[0], [2, 4, 3, 6]);
+        assert_eq!(inspections[19], [99, 97, 8, 103]);
+        assert_eq!(inspections[999], [5204, 4792, 199, 5192]);
+    }
+
+    #[test]
+    fn only_the_unreduced_worry_levels_overflow() {
+        let monkeys = parse_monkeys(EXAMPLE).unwrap();
+        let divisor = base_divisor(&monkeys);
+
+        let error =
+            run_rounds_collecting(&mut monkeys.clone(), 10_000, &WorryReduction::None).unwrap_err();
+        let round = error
+            .strip_prefix("Round ")
+            .and_then(|rest| rest.split(':').next())
+            .and_then(|round| round.parse::<usize>().ok())
+            .unwrap();
+
+        // Monkey 2 squares the worry levels, so they overflow long before the 10000 rounds.
+        assert!(round < 100, "{error}");
+        assert!(run_rounds_collecting(
+            &mut monkeys.clone(),
+            10_000,
+            &WorryReduction::Modulo(divisor)
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn scores_the_example_monkey_business() {
+        let monkeys = parse_monkeys(EXAMPLE).unwrap();
+        let divisor = base_divisor(&monkeys);
+
+        let part1 = run_rounds_collecting(&mut monkeys.clone(), 20, &WorryReduction::DivideByThree)
+            .unwrap();
+        let part2 = run_rounds_collecting(
+            &mut monkeys.clone(),
+            10_000,
+            &WorryReduction::Modulo(divisor),
+        )
+        .unwrap();
+
+        assert_eq!(monkey_business(part1.last().unwrap(), 2), 10605);
+        assert_eq!(monkey_business(part2.last().unwrap(), 2), 2713310158);
+        assert_eq!(monkey_business(&[101, 95, 7, 105], 3), 105 * 101 * 95);
+        assert_eq!(monkey_business(&[101, 95, 7, 105], 1), 105);
+    }
+
+    #[test]
+    fn overflowing_worry_level_is_an_error() {
+        let mut monkeys = parse_monkeys(
+            "Monkey 0:
+  Starting items: 100000000000000000000000000000000000000
+  Operation: new = old * 19
+  Test: divisible by 23
+    If true: throw to monkey 1
+    If false: throw to monkey 1
+
+Monkey 1:
+  Starting items: 1
+  Operation: new = old + 1
+  Test: divisible by 2
+    If true: throw to monkey 0
+    If false: throw to monkey 0",
+        )
+        .unwrap();
+
+        let error = run_round(&mut monkeys, &WorryReduction::DivideByThree).unwrap_err();
+
+        assert!(error.starts_with("Worry level overflow"), "{error}");
+        assert!(
+            error.contains("100000000000000000000000000000000000000"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn throw_targets_have_to_exist() {
+        let input = EXAMPLE.replacen("throw to monkey 3", "throw to monkey 4", 1);
+
+        assert_eq!(
+            parse_monkeys(&input).err(),
+            Some(ParseError::new(6, "There is no monkey 4 to throw to"))
+        );
+    }
 }
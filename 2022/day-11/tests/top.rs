@@ -0,0 +1,33 @@
+use std::process::{Command, Output};
+
+/// Run the solution against the sample with the given `--top` value.
+fn top(value: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_day-11"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["--example", "--top", value])
+        .output()
+        .expect("Couldn't run day-11")
+}
+
+#[test]
+fn multiplies_the_requested_number_of_monkeys() {
+    let output = top("1");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "105\n52166\n");
+}
+
+#[test]
+fn rejects_invalid_and_zero_values() {
+    for (value, error) in [
+        ("x", "Invalid value \"x\" for --top\n"),
+        ("-1", "Invalid value \"-1\" for --top\n"),
+        ("0", "The --top flag expects at least one monkey\n"),
+    ] {
+        let output = top(value);
+
+        assert_eq!(output.status.code(), Some(1));
+        assert!(output.stdout.is_empty());
+        assert_eq!(String::from_utf8(output.stderr).unwrap(), error);
+    }
+}
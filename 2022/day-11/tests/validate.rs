@@ -0,0 +1,62 @@
+use std::process::{Command, Output};
+
+/// Run the solution with `--validate` from the given directory.
+fn validate(directory: &std::path::Path, flags: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_day-11"))
+        .current_dir(directory)
+        .arg("--validate")
+        .args(flags)
+        .output()
+        .expect("Couldn't run day-11")
+}
+
+#[test]
+fn validates_the_sample() {
+    let output = validate(env!("CARGO_MANIFEST_DIR").as_ref(), &["--example"]);
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "ok, 4 monkeys\n");
+}
+
+#[test]
+fn reports_the_first_bad_line() {
+    // Break a line of the sample and validate it as the input of a separate directory.
+    let sample = include_str!("../sample.txt");
+    let directory = std::env::temp_dir().join("day-11-bad-input");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("input.txt"),
+        sample.replacen("Test: divisible by 19", "Test: divisible by x", 1),
+    )
+    .unwrap();
+
+    let output = validate(&directory, &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Line 11: Expected a number at the end of \"Test: divisible by x\"\n"
+    );
+}
+
+#[test]
+fn reports_a_throw_to_a_missing_monkey() {
+    let sample = include_str!("../sample.txt");
+    let directory = std::env::temp_dir().join("day-11-missing-monkey");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("input.txt"),
+        sample.replacen("throw to monkey 0", "throw to monkey 7", 1),
+    )
+    .unwrap();
+
+    let output = validate(&directory, &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Line 13: There is no monkey 7 to throw to\n"
+    );
+}
@@ -1,10 +1,11 @@
 use std::collections::HashMap;
 
+use aoc_common::{extra_output, input_filename, parse_grid, print_answers, read_input, MinHeap};
+
 /// A struct which represents a point in the heightmap.
 #[derive(PartialEq, Eq, Hash, Debug, Clone)]
 struct Node {
     coords: (usize, usize),
-    distance: usize,
     height: u8,
     start: bool,
     end: bool,
@@ -14,164 +15,201 @@ impl Node {
     /// Create a new node given the coordinates and the height of the point as a character.
     pub fn new(x: usize, y: usize, character: char) -> Self {
         let height = match character {
-            'S' => 'a' as u8,
-            'E' => 'z' as u8,
+            'S' => b'a',
+            'E' => b'z',
             other => other as u8,
         };
 
         Self {
             coords: (x, y),
-            distance: 0,
-            height: height - 'a' as u8,
+            height: height - b'a',
             start: character == 'S',
             end: character == 'E',
         }
     }
 }
 
-/// Create heightmap from the input file and collect width and height of input.
-fn read_map(filename: &str) -> (HashMap<(usize, usize), Node>, (usize, usize)) {
-    let mut x = 0;
-
-    let map = std::fs::read_to_string(filename)
-        .unwrap()
-        .lines()
-        .enumerate()
-        .map(|(y, line)| {
-            x = line.len();
-
-            line.chars()
-                .enumerate()
-                .map(|(x, character)| ((x, y), Node::new(x, y, character)))
-                .collect::<Vec<_>>()
-        })
+/// The heightmap - the nodes by their coordinates.
+type Heightmap = HashMap<(usize, usize), Node>;
+
+/// Create heightmap from the input and collect width and height of input.
+fn parse_map(input: &str) -> Result<(Heightmap, (usize, usize)), String> {
+    let (grid, dimensions) = parse_grid(input, Node::new)?;
+
+    let map = grid
+        .into_iter()
         .flatten()
-        .collect::<HashMap<(usize, usize), Node>>();
+        .map(|node| (node.coords, node))
+        .collect();
 
-    let y = map.len() / x;
+    Ok((map, dimensions))
+}
 
-    (map, (x, y))
+/// Create heightmap from the input file.
+fn read_map(filename: &str) -> Result<(Heightmap, (usize, usize)), String> {
+    parse_map(&read_input(filename)?)
 }
 
-/// Find the distance from the `Start` node to the `End` node using BFS (breadth first search).
-fn calculate_distance(
-    map: &HashMap<(usize, usize), Node>,
+/// Get the coordinates of the left, right, bottom and top neighbors of a point which are within
+/// the borders of the map. A point outside of the map has no neighbors.
+fn neighbors4(
+    (x, y): (usize, usize),
     (max_x, max_y): (usize, usize),
-) -> usize {
-    // Create a visited set.
-    let mut visited = HashMap::<(usize, usize), Node>::new();
+) -> impl Iterator<Item = (usize, usize)> {
+    let in_map = x < max_x && y < max_y;
+
+    [
+        (x.checked_sub(1), Some(y)),
+        (Some(x + 1), Some(y)),
+        (Some(x), y.checked_sub(1)),
+        (Some(x), Some(y + 1)),
+    ]
+    .into_iter()
+    .filter_map(move |neighbor| match neighbor {
+        (Some(x), Some(y)) if in_map && x < max_x && y < max_y => Some((x, y)),
+        _ => None,
+    })
+}
 
-    // Find the `Start` node.
-    let node = map.iter().find(|(_, node)| node.start).unwrap().1.clone();
+/// The direction we climb in while searching the heightmap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Climb {
+    /// Climb up from a start point - we can move to a neighbor at most one point heigher.
+    Up,
+    /// Climb down from an end point, reversing the rule - we can move to a neighbor at most one
+    /// point lower.
+    Down,
+}
+
+/// Find the distances from the given start point to all of the points reachable from it using
+/// Dijkstra's algorithm, always visiting the closest node in the queue next.
+fn distances_from(
+    map: &Heightmap,
+    start: (usize, usize),
+    (max_x, max_y): (usize, usize),
+    climb: Climb,
+) -> HashMap<(usize, usize), usize> {
+    // Create a visited map which holds the distances of the visited nodes.
+    let mut visited = HashMap::<(usize, usize), usize>::new();
 
     // Create a visitation queue with the start node as the first element.
-    let mut next_to_visit = vec![node];
+    let mut next_to_visit = MinHeap::new();
+    next_to_visit.push(0, start);
 
     // Loop while the visitation queue is not empty.
-    while !next_to_visit.is_empty() {
-        let next_node = next_to_visit.remove(0);
-        let coords = next_node.coords;
-
+    while let Some((distance, coords)) = next_to_visit.pop() {
         // If this node is already visited just skip adding it's neighbors to the queue.
         if visited.contains_key(&coords) {
             continue;
         }
 
-        // Check if we are at the left border of the map.
-        if coords.0 > 0 {
-            let mut neighbor = map.get(&(coords.0 - 1, coords.1)).unwrap().clone();
-
-            // Add the neigbor if we can move to it - e.g. if the neighbor is not more than one
-            // point heigher.
-            if next_node.height + 1 >= neighbor.height {
-                neighbor.distance = next_node.distance + 1;
-                next_to_visit.push(neighbor);
-            }
-        }
-
-        // Check if we are at the right border of the map.
-        if coords.0 + 1 < max_x {
-            let mut neighbor = map.get(&(coords.0 + 1, coords.1)).unwrap().clone();
-
-            // Add the neigbor if we can move to it - e.g. if the neighbor is not more than one
-            // point heigher.
-            if next_node.height + 1 >= neighbor.height {
-                neighbor.distance = next_node.distance + 1;
-                next_to_visit.push(neighbor);
-            }
-        }
-
-        // Check if we are at the bottom border of the map.
-        if coords.1 > 0 {
-            let mut neighbor = map.get(&(coords.0, coords.1 - 1)).unwrap().clone();
-
-            // Add the neigbor if we can move to it - e.g. if the neighbor is not more than one
-            // point heigher.
-            if next_node.height + 1 >= neighbor.height {
-                neighbor.distance = next_node.distance + 1;
-                next_to_visit.push(neighbor);
-            }
-        }
+        let node = map.get(&coords).unwrap();
 
-        // Check if we are at the top border of the map.
-        if coords.1 + 1 < max_y {
-            let mut neighbor = map.get(&(coords.0, coords.1 + 1)).unwrap().clone();
+        // Go through all the neighbors within the borders of the map.
+        for neighbor_coords in neighbors4(coords, (max_x, max_y)) {
+            let neighbor = map.get(&neighbor_coords).unwrap();
 
             // Add the neigbor if we can move to it - e.g. if the neighbor is not more than one
-            // point heigher.
-            if next_node.height + 1 >= neighbor.height {
-                neighbor.distance = next_node.distance + 1;
-                next_to_visit.push(neighbor.clone());
+            // point heigher when climbing up, or lower when climbing down.
+            let can_move = match climb {
+                Climb::Up => node.height + 1 >= neighbor.height,
+                Climb::Down => neighbor.height + 1 >= node.height,
+            };
+
+            if can_move {
+                next_to_visit.push(distance + 1, neighbor_coords);
             }
         }
 
-        visited.insert(coords, next_node);
+        visited.insert(coords, distance);
     }
 
-    // Find the distance from the start node to the end node - return the max distance if the end
-    // node is not reachable from the start.
-    if let Some((_, node)) = visited.iter().find(|(_, node)| node.end) {
-        node.distance
-    } else {
-        std::usize::MAX
-    }
+    visited
 }
 
-/// Go through all low points in the map to find the best start point.
-fn find_best_starting_point(map: &HashMap<(usize, usize), Node>, ranges: (usize, usize)) -> usize {
-    // Make a clone of our map.
-    let mut clone = map.clone();
+/// Find the distance from the `Start` node to the `End` node - return the max distance if the end
+/// node is not reachable from the start.
+fn calculate_distance(map: &Heightmap, ranges: (usize, usize)) -> usize {
+    let start = map.values().find(|node| node.start).unwrap();
+    let end = map.values().find(|node| node.end).unwrap();
 
-    // Turn the given start point to a regular low point.
-    let start = clone.iter_mut().find(|(_, node)| node.start).unwrap();
-    start.1.start = false;
+    distances_from(map, start.coords, ranges, Climb::Up)
+        .get(&end.coords)
+        .copied()
+        .unwrap_or(usize::MAX)
+}
 
-    // Iterate over the low points of the map and find the minimum distance from end node.
-    map.iter()
-        .filter_map(|(coords, node)| match node.height {
-            0 => {
-                // Make the current node a start node in the clone map.
-                let clone_node = clone.get_mut(&coords).unwrap();
-                clone_node.start = true;
+/// Find the distance from the closest low point to the `End` node, by climbing down from the `End`
+/// node once and checking the distances of all the low points.
+fn find_best_starting_point(map: &Heightmap, ranges: (usize, usize)) -> usize {
+    let end = map.values().find(|node| node.end).unwrap();
 
-                // Get the distance from this start node to the end node.
-                let result = calculate_distance(&clone, ranges);
+    distances_from(map, end.coords, ranges, Climb::Down)
+        .iter()
+        .filter(|(coords, _)| map.get(coords).unwrap().height == 0)
+        .map(|(_, distance)| *distance)
+        .min()
+        .unwrap_or(usize::MAX)
+}
 
-                // Revert the current node into a low point node in the clone map.
-                let clone_node = clone.get_mut(&coords).unwrap();
-                clone_node.start = false;
+/// Render the heightmap with a letter for the height of each point, and `S` and `E` for the start
+/// and end points.
+fn render_heightmap(map: &Heightmap, (max_x, max_y): (usize, usize)) -> String {
+    (0..max_y)
+        .map(|y| {
+            (0..max_x)
+                .map(|x| match map.get(&(x, y)) {
+                    Some(node) if node.start => 'S',
+                    Some(node) if node.end => 'E',
+                    Some(node) => (b'a' + node.height) as char,
+                    None => ' ',
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
 
-                Some(result)
-            }
-            _ => None,
+/// Render the distance of each point modulo 10, with `.` for the points which can't be reached.
+fn render_distances(
+    distances: &HashMap<(usize, usize), usize>,
+    (max_x, max_y): (usize, usize),
+) -> String {
+    (0..max_y)
+        .map(|y| {
+            (0..max_x)
+                .map(|x| match distances.get(&(x, y)) {
+                    Some(distance) => char::from_digit((distance % 10) as u32, 10).unwrap(),
+                    None => '.',
+                })
+                .collect::<String>()
         })
-        .min()
-        .unwrap()
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 fn main() {
     // Read the heightmap from the input file.
-    let (map, ranges) = read_map("input.txt");
+    let (map, ranges) = match read_map(input_filename()) {
+        Ok(map) => map,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+
+    // Render the heightmap if requested.
+    if extra_output("--render") {
+        println!("{}\n", render_heightmap(&map, ranges));
+    }
+
+    // Render the distances from the starting node if requested.
+    if extra_output("--distances") {
+        let start = map.values().find(|node| node.start).unwrap();
+        let distances = distances_from(&map, start.coords, ranges, Climb::Up);
+
+        println!("{}\n", render_distances(&distances, ranges));
+    }
 
     // Get the distance of the starting node to the end node.
     let distance = calculate_distance(&map, ranges);
@@ -179,6 +217,73 @@ fn main() {
     // Get the closest low point node's distance to the end node.
     let min_distance = find_best_starting_point(&map, ranges);
 
-    println!("{distance}");
-    println!("{min_distance}");
+    print_answers(12, distance, min_distance);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "Sabqponm\nabcryxxl\naccszExk\nacctuvwj\nabdefghi\n";
+
+    #[test]
+    fn neighbors_stay_within_the_map() {
+        let ranges = (8, 5);
+
+        assert_eq!(neighbors4((0, 0), ranges).count(), 2);
+        assert_eq!(neighbors4((7, 4), ranges).count(), 2);
+        assert_eq!(neighbors4((3, 0), ranges).count(), 3);
+        assert_eq!(neighbors4((3, 4), ranges).count(), 3);
+        assert_eq!(
+            neighbors4((3, 2), ranges).collect::<Vec<_>>(),
+            [(2, 2), (4, 2), (3, 1), (3, 3)]
+        );
+        assert_eq!(neighbors4((8, 0), ranges).count(), 0);
+    }
+
+    #[test]
+    fn distances_from_the_end_find_the_best_start() {
+        let (map, ranges) = parse_map(EXAMPLE).unwrap();
+        let start = map.values().find(|node| node.start).unwrap().coords;
+        let end = map.values().find(|node| node.end).unwrap().coords;
+
+        let from_start = distances_from(&map, start, ranges, Climb::Up);
+        assert_eq!(from_start.get(&end), Some(&31));
+
+        let from_end = distances_from(&map, end, ranges, Climb::Down);
+        assert_eq!(from_end.get(&start), Some(&31));
+        assert_eq!(
+            from_end
+                .iter()
+                .filter(|(coords, _)| map[*coords].height == 0)
+                .map(|(_, distance)| *distance)
+                .min(),
+            Some(29)
+        );
+        assert_eq!(find_best_starting_point(&map, ranges), 29);
+    }
+
+    #[test]
+    fn renders_start_and_end_in_place() {
+        let (map, ranges) = parse_map(EXAMPLE).unwrap();
+
+        let heightmap = render_heightmap(&map, ranges);
+        let rows = heightmap.lines().collect::<Vec<_>>();
+        assert_eq!(heightmap, EXAMPLE.trim_end());
+        assert_eq!(rows[0].find('S'), Some(0));
+        assert_eq!(rows[2].find('E'), Some(5));
+
+        // The end is 31 steps away from the start, and every point can be reached.
+        let start = map.values().find(|node| node.start).unwrap().coords;
+        let distances = render_distances(&distances_from(&map, start, ranges, Climb::Up), ranges);
+        let rows = distances.lines().collect::<Vec<_>>();
+        assert_eq!(rows[0].chars().next(), Some('0'));
+        assert_eq!(rows[2].chars().nth(5), Some('1'));
+        assert!(!distances.contains('.'));
+
+        // Nothing can be reached from a point surrounded by much higher points.
+        let (map, ranges) = parse_map("zzz\nzSz\nzzE\n").unwrap();
+        let distances = distances_from(&map, (1, 1), ranges, Climb::Up);
+        assert_eq!(render_distances(&distances, ranges), "...\n.0.\n...");
+    }
 }
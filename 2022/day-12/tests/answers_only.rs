@@ -0,0 +1,31 @@
+use std::process::Command;
+
+/// Run the solution on the sample input with the given flags and collect its output lines.
+fn run_example(flags: &[&str]) -> Vec<String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_day-12"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg("--example")
+        .args(flags)
+        .output()
+        .expect("Couldn't run day-12");
+
+    assert!(output.status.success());
+
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn answers_only_prints_just_the_answers() {
+    assert!(run_example(&["--render", "--distances"]).len() > 2);
+
+    for flag in ["--answers-only", "--quiet"] {
+        assert_eq!(
+            run_example(&["--render", "--distances", flag]),
+            ["31", "29"]
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use std::process::Command;
+
+#[test]
+fn example_flag_reads_the_sample() {
+    let output = Command::new(env!("CARGO_BIN_EXE_day-12"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg("--example")
+        .output()
+        .expect("Couldn't run day-12");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "31\n29\n");
+}
+
+#[test]
+fn example_flag_without_a_sample_is_an_error() {
+    // Run the solution from a directory without a sample input.
+    let directory = std::env::temp_dir().join("day-12-without-a-sample");
+    std::fs::create_dir_all(&directory).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_day-12"))
+        .current_dir(&directory)
+        .arg("--example")
+        .output()
+        .expect("Couldn't run day-12");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "There is no sample input (sample.txt) for this day.\n"
+    );
+}
@@ -1,5 +1,11 @@
+use std::{fmt::Display, str::FromStr};
+
+use aoc_common::{
+    extra_output, input_filename, print_answers, read_input, report_error, verbose, ParseError,
+};
+
 /// An enum that represents a list of integers or lists.
-#[derive(Eq, PartialOrd, Clone, Debug)]
+#[derive(Eq, Clone, Debug)]
 enum Item {
     Integer(i32),
     List(Vec<Item>),
@@ -26,107 +32,72 @@ fn get_outmost_bracket_pair(string: &str) -> Option<(usize, bool)> {
         return None;
     }
 
-    return None;
+    None
 }
 
-impl Item {
-    /// Check if the item is an integer.
-    pub fn is_integer(&self) -> bool {
-        match self {
-            Self::Integer(_) => true,
-            _ => false,
-        }
-    }
+/// Parse a single item from the start of the characters with a recursive descent parser. Returns
+/// the item along with the characters that follow it.
+fn parse_item(chars: &[char]) -> Result<(Item, &[char]), String> {
+    match chars.first() {
+        Some('[') => {
+            let mut rest = &chars[1..];
+            let mut items = vec![];
 
-    /// Returns the list of an item if the item is of the List variant.
-    pub fn get_list(self) -> Option<Vec<Item>> {
-        match self {
-            Self::List(list) => Some(list),
-            _ => None,
-        }
-    }
+            // An empty list has nothing to parse before the closing bracket.
+            if rest.first() == Some(&']') {
+                return Ok((Item::List(items), &rest[1..]));
+            }
 
-    /// Parse an item from a line of text by recursively parsing lists until we get to either an
-    /// empty list or an integer.
-    pub fn new(string: &str) -> Self {
-        // If string is empty return an empty list.
-        if string.is_empty() {
-            return Self::List(vec![]);
+            // Parse items until the closing bracket, each separated by a comma.
+            loop {
+                let (item, after) = parse_item(rest)?;
+                items.push(item);
+
+                match after.first() {
+                    Some(',') => rest = &after[1..],
+                    Some(']') => return Ok((Item::List(items), &after[1..])),
+                    Some(char) => return Err(format!("Expected ',' or ']' but found '{char}'")),
+                    None => return Err("Unclosed list at the end of the packet".to_string()),
+                }
+            }
         }
+        Some(char) if char.is_ascii_digit() => {
+            let length = chars
+                .iter()
+                .take_while(|char| char.is_ascii_digit())
+                .count();
+            let integer = chars[..length]
+                .iter()
+                .collect::<String>()
+                .parse()
+                .map_err(|error| format!("Invalid integer: {error}"))?;
 
-        // If we don't have any nested lists, return a list of integers.
-        if !string.contains("[") {
-            return Self::List(
-                string
-                    .split(",")
-                    .map(|int| Self::Integer(int.parse().unwrap()))
-                    .collect(),
-            );
+            Ok((Item::Integer(integer), &chars[length..]))
         }
+        Some(char) => Err(format!("Expected '[' or an integer but found '{char}'")),
+        None => Err("Unexpected end of the packet".to_string()),
+    }
+}
 
-        // Get the outermost bracket pair is there is one and check if it wraps the current string.
-        let split_index = if let Some((closing_index, wrapped)) = get_outmost_bracket_pair(string) {
-            // If the current string is wrapped with brackets continue parsing inside.
-            if wrapped {
-                let inner_items = Self::new(string.get(1..closing_index).unwrap());
+impl FromStr for Item {
+    type Err = String;
 
-                // If it was just an integer inside, wrap it in a list and return it.
-                if inner_items.is_integer() {
-                    return Self::List(vec![inner_items]);
-                }
+    /// Parse an item from a packet, ignoring any whitespace in it.
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        let chars = string
+            .chars()
+            .filter(|char| !char.is_whitespace())
+            .collect::<Vec<_>>();
+        let (item, rest) = parse_item(&chars)?;
 
-                // If it was a list we have two scenarios.
-                return match get_outmost_bracket_pair(string.get(1..closing_index).unwrap()) {
-                    // If the inner list was wrapped in brackets, put the elements back in
-                    // a bracket and wrap that with a new list and return it.
-                    Some((_, true)) => Self::List(vec![inner_items]),
-                    // Otherwise just return the contents wrapped in a list.
-                    // This could be a list of integers or lists, or a mix.
-                    _ => inner_items,
-                };
-            }
+        if !rest.is_empty() {
+            return Err(format!(
+                "Unexpected \"{}\" after the packet",
+                rest.iter().collect::<String>()
+            ));
+        }
 
-            // If the string wasn't wrapped but started with a bracket, return the index of the
-            // comma after the matching closing bracket
-            closing_index + 1
-        } else {
-            // If the string doesn't start with a bracket, it means there are brackets after the
-            // first element, so we find the index of the first comma.
-            string.find(",").unwrap()
-        };
-
-        // We split the string at the first comma.
-        let (first, rest) = string.split_at(split_index);
-        // We skip the first character of the rest of the string because it is a comma.
-        let rest_of_string = rest.get(1..).unwrap();
-
-        // We parse the first item, it could be a list or integer but we don't need to know.
-        let first_item = Self::new(first);
-
-        // We parse the rest of the items, for these we want to know if they were
-        let rest_of_items = Self::new(rest_of_string);
-
-        // If the rest of the items is just one integer, put the first an second item into a
-        // vector.
-        Self::List(if rest_of_items.is_integer() {
-            vec![first_item, rest_of_items]
-        } else {
-            // If the rest of the items are a list we want to know if the list is just multiple
-            // siblings, or a sublist.
-            match get_outmost_bracket_pair(rest_of_string) {
-                // If it is a sublist, we treat it as a single sibling just like in the integer
-                // scenario.
-                Some((_, true)) => vec![first_item, rest_of_items],
-                // If it is multiple siblings, we insert the first item at the start and return the
-                // modified list of items.
-                _ => {
-                    let mut items = rest_of_items.get_list().unwrap();
-                    items.insert(0, first_item);
-
-                    items
-                }
-            }
-        })
+        Ok(item)
     }
 }
 
@@ -141,95 +112,551 @@ impl PartialEq for Item {
     }
 }
 
+impl PartialOrd for Item {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Ord for Item {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         match (self, other) {
             (Item::Integer(left), Item::Integer(right)) => left.cmp(right),
-            (Item::List(left), Item::List(right)) => left.cmp(&right),
+            (Item::List(left), Item::List(right)) => left.cmp(right),
             (Item::Integer(left), Item::List(right)) => vec![Item::Integer(*left)].cmp(right),
             (Item::List(left), Item::Integer(right)) => left.cmp(&vec![Item::Integer(*right)]),
         }
     }
 }
 
-/// Read the packet pairs from the input file into pairs of items.
-fn read_packet_pairs(filename: &str) -> Vec<(Item, Item)> {
-    std::fs::read_to_string(filename)
-        .unwrap()
+impl Display for Item {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Integer(integer) => write!(f, "{integer}"),
+            Self::List(list) => {
+                let items = list.iter().map(|item| item.to_string()).collect::<Vec<_>>();
+
+                write!(f, "[{}]", items.join(","))
+            }
+        }
+    }
+}
+
+/// Compare two items while logging each step of the comparison, indented by the depth of the
+/// comparison.
+fn explain_comparison(
+    left: &Item,
+    right: &Item,
+    depth: usize,
+    log: &mut Vec<String>,
+) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    let indent = "  ".repeat(depth);
+    let inner_indent = "  ".repeat(depth + 1);
+
+    log.push(format!("{indent}- Compare {left} vs {right}"));
+
+    match (left, right) {
+        (Item::Integer(left), Item::Integer(right)) => {
+            match left.cmp(right) {
+                Ordering::Less => log.push(format!(
+                    "{inner_indent}- Left side is smaller, so inputs are in the right order"
+                )),
+                Ordering::Greater => log.push(format!(
+                    "{inner_indent}- Right side is smaller, so inputs are not in the right order"
+                )),
+                Ordering::Equal => {}
+            }
+
+            left.cmp(right)
+        }
+        (Item::List(left), Item::List(right)) => {
+            for index in 0.. {
+                match (left.get(index), right.get(index)) {
+                    (Some(left), Some(right)) => {
+                        let ordering = explain_comparison(left, right, depth + 1, log);
+
+                        if ordering != Ordering::Equal {
+                            return ordering;
+                        }
+                    }
+                    (None, Some(_)) => {
+                        log.push(format!(
+                            "{inner_indent}- Left side ran out of items, so inputs are in the right order"
+                        ));
+
+                        return Ordering::Less;
+                    }
+                    (Some(_), None) => {
+                        log.push(format!(
+                            "{inner_indent}- Right side ran out of items, so inputs are not in the right order"
+                        ));
+
+                        return Ordering::Greater;
+                    }
+                    (None, None) => break,
+                }
+            }
+
+            Ordering::Equal
+        }
+        (Item::Integer(integer), Item::List(_)) => {
+            log.push(format!(
+                "{inner_indent}- Mixed types; convert left to [{integer}] and retry comparison"
+            ));
+
+            explain_comparison(
+                &Item::List(vec![Item::Integer(*integer)]),
+                right,
+                depth + 1,
+                log,
+            )
+        }
+        (Item::List(_), Item::Integer(integer)) => {
+            log.push(format!(
+                "{inner_indent}- Mixed types; convert right to [{integer}] and retry comparison"
+            ));
+
+            explain_comparison(
+                left,
+                &Item::List(vec![Item::Integer(*integer)]),
+                depth + 1,
+                log,
+            )
+        }
+    }
+}
+
+/// Explain why a pair of packets is in the right order or not. Returns the decision along with a
+/// log of the comparison steps, worded like the puzzle walkthrough.
+fn explain(left: &Item, right: &Item) -> (std::cmp::Ordering, Vec<String>) {
+    let mut log = vec![];
+    let ordering = explain_comparison(left, right, 0, &mut log);
+
+    (ordering, log)
+}
+
+/// Split a block of text into its top level packets. Whitespace and newlines are stripped first so
+/// that a packet may be spread across multiple lines. Any other text outside of the packets is an
+/// error.
+fn split_packets(block: &str) -> Result<Vec<String>, String> {
+    let mut rest = block
+        .chars()
+        .filter(|char| !char.is_whitespace())
+        .collect::<String>();
+    let mut packets = vec![];
+
+    while let Some((closing_index, _)) = get_outmost_bracket_pair(&rest) {
+        let remainder = rest.split_off(closing_index + 1);
+
+        packets.push(rest);
+        rest = remainder;
+    }
+
+    if !rest.is_empty() {
+        return Err(format!("Unexpected \"{rest}\" outside of the packets"));
+    }
+
+    Ok(packets)
+}
+
+/// Parse the packet pairs, separated by empty lines, into pairs of items. Each block has to hold
+/// exactly two packets. Returns an error with the line number the offending pair starts at.
+fn parse_packet_pairs(input: &str) -> Result<Vec<(Item, Item)>, ParseError> {
+    // Keep track of the line number the current pair starts at.
+    let mut first_line = 1;
+
+    input
         .split("\n\n")
-        .map(|packets| {
-            let lines = packets.lines().collect::<Vec<_>>();
-            let first_packet = Item::new(lines.first().unwrap());
-            let second_packet = Item::new(lines.last().unwrap());
+        .map(|block| {
+            let line = first_line;
+            first_line += block.lines().count() + 1;
+
+            (line, block)
+        })
+        .filter(|(_, block)| !block.trim().is_empty())
+        .map(|(line, block)| {
+            let packets = split_packets(block).map_err(|error| ParseError::new(line, error))?;
+            let [left, right] = &packets[..] else {
+                return Err(ParseError::new(
+                    line,
+                    format!("Expected 2 packets, found {}", packets.len()),
+                ));
+            };
+            let parse = |packet: &String| {
+                packet
+                    .parse::<Item>()
+                    .map_err(|error| ParseError::new(line, error))
+            };
 
-            (first_packet, second_packet)
+            Ok((parse(left)?, parse(right)?))
         })
         .collect()
 }
 
-/// Filter through the pairs of packets to find the correctly ordered pairs and return their index
-/// - the index starts at 1 so we add 1 to the actual iterator's index.
+/// Filter through the pairs of packets to find the correctly ordered pairs and return their
+/// index - the index starts at 1 so we add 1 to the actual iterator's index. A pair of equal packets
+/// never decides the order, so it isn't counted as being in the right order.
 fn find_right_order_pair_indices(pairs: &[(Item, Item)]) -> Vec<usize> {
     pairs
         .iter()
         .enumerate()
         .filter_map(|(index, (first, second))| match first.cmp(second) {
-            std::cmp::Ordering::Greater => None,
-            std::cmp::Ordering::Equal => panic!("not expected"),
             std::cmp::Ordering::Less => Some(index + 1),
+            std::cmp::Ordering::Equal | std::cmp::Ordering::Greater => None,
         })
         .collect()
 }
 
-/// Read all the packets from the input file while ignoring pairings / empty lines.
-fn read_packets(filename: &str) -> Vec<Item> {
-    std::fs::read_to_string(filename)
-        .unwrap()
-        .lines()
-        .filter_map(|line| {
-            if line.is_empty() {
-                None
-            } else {
-                Some(Item::new(line))
-            }
+/// Find the pairs of indices of equal packets, with the smaller index first.
+fn find_duplicates(packets: &[Item]) -> Vec<(usize, usize)> {
+    packets
+        .iter()
+        .enumerate()
+        .flat_map(|(first, packet)| {
+            packets
+                .iter()
+                .enumerate()
+                .skip(first + 1)
+                .filter(move |(_, other)| *other == packet)
+                .map(move |(second, _)| (first, second))
         })
         .collect()
 }
 
-fn main() {
-    // Get the packet pairs.
-    let pairs = read_packet_pairs("input.txt");
-    // Get the indices of the correctly ordered packet pairs.
-    let indices = find_right_order_pair_indices(&pairs);
-    // Sum the bracket pair indices.
-    let sum = indices.iter().sum::<usize>();
-
-    // Get all the packets.
-    let mut packets = read_packets("input.txt");
+/// Get the decoder key - the product of the indices of the divider packets once they are added to
+/// the packets and everything is sorted, with the indices starting at 1.
+fn decoder_key(mut packets: Vec<Item>) -> usize {
     // Create the divider packets.
-    let two_packet = Item::new("[[2]]");
-    let six_packet = Item::new("[[6]]");
+    let two_packet = "[[2]]".parse::<Item>().unwrap();
+    let six_packet = "[[6]]".parse::<Item>().unwrap();
 
     // Insert the divider packets into our list.
     packets.extend([two_packet.clone(), six_packet.clone()]);
 
-    // Sort the packets vector.
-    packets.sort_unstable_by(|left, right| left.cmp(right));
+    // Sort the packets vector. The sort is stable, so the divider packets stay after any packets
+    // equal to them since they were inserted last.
+    packets.sort();
 
-    // Find the index of the first divider packet.
-    let (index_two, _) = packets
+    // Find the index of the first divider packet - the last packet equal to it.
+    let index_two = packets
         .iter()
-        .enumerate()
-        .find(|(_, packet)| packet == &&two_packet)
+        .rposition(|packet| packet == &two_packet)
         .unwrap();
 
-    // Find the index of the second divider packet.
-    let (index_six, _) = packets
+    // Find the index of the second divider packet - the last packet equal to it.
+    let index_six = packets
         .iter()
-        .enumerate()
-        .find(|(_, packet)| packet == &&six_packet)
+        .rposition(|packet| packet == &six_packet)
         .unwrap();
 
-    println!("{sum}");
-    println!("{}", (index_six + 1) * (index_two + 1));
+    (index_six + 1) * (index_two + 1)
+}
+
+fn main() {
+    // Get the packet pairs.
+    let input = match read_input(input_filename()) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    let pairs = match parse_packet_pairs(&input) {
+        Ok(pairs) => pairs,
+        Err(error) => {
+            eprintln!("{}", report_error(&error, &input, verbose()));
+            std::process::exit(1);
+        }
+    };
+    // Get the indices of the correctly ordered packet pairs.
+    let indices = find_right_order_pair_indices(&pairs);
+    // Sum the bracket pair indices.
+    let sum = indices.iter().sum::<usize>();
+
+    // Explain the order of each pair if requested.
+    if extra_output("--explain") {
+        for (index, (left, right)) in pairs.iter().enumerate() {
+            println!("== Pair {} ==", index + 1);
+            println!("{}\n", explain(left, right).1.join("\n"));
+        }
+    }
+
+    // Get all the packets from the pairs, so that the input is only parsed once.
+    let packets = pairs
+        .into_iter()
+        .flat_map(|(left, right)| [left, right])
+        .collect::<Vec<_>>();
+    // Print the equal packets in the input if requested.
+    if extra_output("--duplicates") {
+        let duplicates = find_duplicates(&packets);
+
+        if duplicates.is_empty() {
+            println!("No packets are equal");
+        }
+
+        for (first, second) in duplicates {
+            println!(
+                "Packets {} and {} are equal: {}",
+                first + 1,
+                second + 1,
+                packets[first]
+            );
+        }
+
+        println!();
+    }
+
+    print_answers(13, sum, decoder_key(packets));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse each of the packets.
+    fn parse_packets(packets: &[&str]) -> Vec<Item> {
+        packets
+            .iter()
+            .map(|packet| packet.parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn explains_a_pair_in_the_right_order() {
+        let packets = parse_packets(&["[1,1,3,1,1]", "[1,1,5,1,1]"]);
+
+        let (ordering, log) = explain(&packets[0], &packets[1]);
+
+        assert_eq!(ordering, std::cmp::Ordering::Less);
+        assert_eq!(
+            log,
+            [
+                "- Compare [1,1,3,1,1] vs [1,1,5,1,1]",
+                "  - Compare 1 vs 1",
+                "  - Compare 1 vs 1",
+                "  - Compare 3 vs 5",
+                "    - Left side is smaller, so inputs are in the right order",
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_packets_spread_over_several_lines() {
+        // Each packet of the first pair is spread over three indented lines.
+        let pretty = "[\n  [1], [2, 3, 4]\n]\n[\n  [1], 4\n]\n\n[9]\n[[8,7,6]]\n";
+
+        let pairs = parse_packet_pairs(pretty).unwrap();
+
+        assert_eq!(
+            pairs,
+            parse_packet_pairs("[[1],[2,3,4]]\n[[1],4]\n\n[9]\n[[8,7,6]]\n").unwrap()
+        );
+        assert_eq!(pairs[0].0.to_string(), "[[1],[2,3,4]]");
+        assert_eq!(pairs[0].1.to_string(), "[[1],4]");
+    }
+
+    #[test]
+    fn finds_identical_packets() {
+        let packets = parse_packets(&["[1,[2]]", "[3]", "[1,[2]]", "[]"]);
+
+        assert_eq!(find_duplicates(&packets), vec![(0, 2)]);
+    }
+
+    #[test]
+    fn finds_no_duplicates_in_distinct_packets() {
+        let packets = parse_packets(&["[1,1,3,1,1]", "[1,1,5,1,1]", "[[1],[2,3,4]]", "[[]]", "[]"]);
+
+        assert_eq!(find_duplicates(&packets), vec![]);
+    }
+
+    #[test]
+    fn decoder_key_of_the_example() {
+        let packets = parse_packets(&[
+            "[1,1,3,1,1]",
+            "[1,1,5,1,1]",
+            "[[1],[2,3,4]]",
+            "[[1],4]",
+            "[9]",
+            "[[8,7,6]]",
+            "[[4,4],4,4]",
+            "[[4,4],4,4,4]",
+            "[7,7,7,7]",
+            "[7,7,7]",
+            "[]",
+            "[3]",
+            "[[[]]]",
+            "[[]]",
+            "[1,[2,[3,[4,[5,6,7]]]],8,9]",
+            "[1,[2,[3,[4,[5,6,0]]]],8,9]",
+        ]);
+
+        assert_eq!(decoder_key(packets), 140);
+    }
+
+    #[test]
+    fn decoder_key_is_stable_with_duplicate_dividers() {
+        // Packets equal to the dividers - including `[2]`, which compares equal to `[[2]]` - end up
+        // before the dividers, whatever order they come in.
+        let packets = parse_packets(&["[[6]]", "[1]", "[[2]]", "[2]", "[[6]]", "[[3]]"]);
+        let expected = 4 * 8;
+
+        for rotation in 0..packets.len() {
+            let mut rotated = packets.clone();
+            rotated.rotate_left(rotation);
+
+            assert_eq!(decoder_key(rotated.clone()), expected);
+
+            rotated.reverse();
+            assert_eq!(decoder_key(rotated), expected);
+        }
+    }
+
+    #[test]
+    fn equal_pair_is_not_in_the_right_order() {
+        let packets = parse_packets(&["[1,[2]]", "[1,[2]]", "[1]", "[2]", "[[4]]", "[3]"]);
+        let pairs = packets
+            .chunks(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(find_right_order_pair_indices(&pairs), vec![2]);
+    }
+
+    /// A xorshift random number generator, so that fuzzing is reproducible from a seed without any
+    /// dependencies.
+    struct XorShift(u64);
+
+    impl XorShift {
+        /// Create a generator from a seed. The state must never be zero, so a zero seed is bumped.
+        fn new(seed: u64) -> Self {
+            Self(seed.max(1))
+        }
+
+        /// Get the next random number.
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+
+            self.0
+        }
+
+        /// Get a random number below the given bound.
+        fn below(&mut self, bound: u64) -> u64 {
+            self.next() % bound
+        }
+    }
+
+    /// Generate a random valid packet string, nesting lists at most `depth` levels deep.
+    fn random_packet(rng: &mut XorShift, depth: usize) -> String {
+        let items = (0..rng.below(5))
+            .map(|_| {
+                if depth > 0 && rng.below(3) == 0 {
+                    random_packet(rng, depth - 1)
+                } else {
+                    rng.below(20).to_string()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        format!("[{}]", items.join(","))
+    }
+
+    #[test]
+    fn random_packets_round_trip() {
+        for seed in [1, 2, 42, 2022] {
+            let mut rng = XorShift::new(seed);
+
+            for _ in 0..1_000 {
+                let packet = random_packet(&mut rng, 4);
+                let item = packet.parse::<Item>().unwrap_or_else(|error| {
+                    panic!("Seed {seed}: failed to parse {packet}: {error}")
+                });
+
+                assert_eq!(item.to_string(), packet, "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn corrupted_random_packets_fail_to_parse() {
+        for seed in [1, 2, 42, 2022] {
+            let mut rng = XorShift::new(seed);
+
+            for _ in 0..1_000 {
+                let packet = random_packet(&mut rng, 4);
+
+                // Corrupt the packet by cutting off the closing bracket, adding a stray character
+                // or a stray closing bracket.
+                let position = rng.below(packet.len() as u64 + 1) as usize;
+                let malformed = [
+                    packet[..packet.len() - 1].to_string(),
+                    format!("{}x{}", &packet[..position], &packet[position..]),
+                    format!("{packet}]"),
+                ];
+
+                for malformed in malformed {
+                    assert!(
+                        malformed.parse::<Item>().is_err(),
+                        "Seed {seed}: malformed packet {malformed} was parsed"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_packets_fail_to_parse() {
+        for malformed in [
+            "",
+            "[",
+            "]",
+            "[1,2",
+            "[1,,2]",
+            "[,]",
+            "[1,]",
+            "[a]",
+            "[1]]",
+            "[99999999999]",
+        ] {
+            assert!(
+                malformed.parse::<Item>().is_err(),
+                "Malformed packet {malformed:?} was parsed"
+            );
+        }
+    }
+
+    #[test]
+    fn pairs_need_exactly_two_packets() {
+        assert_eq!(
+            parse_packet_pairs("[1]\n"),
+            Err(ParseError::new(1, "Expected 2 packets, found 1"))
+        );
+        assert_eq!(
+            parse_packet_pairs("[1]\n[2]\n\n[1]\n[2]\n[3]\n"),
+            Err(ParseError::new(4, "Expected 2 packets, found 3"))
+        );
+    }
+
+    #[test]
+    fn text_outside_of_the_packets_is_an_error() {
+        assert_eq!(
+            parse_packet_pairs("[1]\n[2]xyz\n"),
+            Err(ParseError::new(
+                1,
+                "Unexpected \"xyz\" outside of the packets"
+            ))
+        );
+        assert_eq!(
+            parse_packet_pairs("[1]\nfoo\n[2]\n"),
+            Err(ParseError::new(
+                1,
+                "Unexpected \"foo[2]\" outside of the packets"
+            ))
+        );
+        // Whitespace around the packets is still fine.
+        assert!(parse_packet_pairs("  [1]\t\n[2]  \n\n").is_ok());
+    }
 }
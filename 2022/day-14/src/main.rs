@@ -1,4 +1,8 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
+
+use aoc_common::{
+    bounding_box, extra_output, input_filename, parse_flag, print_answers, read_input,
+};
 
 /// An enum representing an item that could block sand from falling further.
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -7,150 +11,488 @@ enum Item {
     Sand,
 }
 
-/// Read the rock locations from the input file line by line and record the locations of the rocks
-/// in a sparse matrix, or in our case a HashMap. We also find the height of the cave.
-fn get_rock_locations(filename: &str) -> (HashMap<(u16, u16), Item>, u16) {
+/// Read the rock locations from the input line by line and record the locations of the rocks in a
+/// sparse matrix, or in our case a HashMap. We also find the height of the cave.
+fn parse_rock_locations(input: &str) -> (HashMap<(u16, u16), Item>, u16) {
     let mut map = HashMap::<(u16, u16), Item>::new();
     let mut height = 0;
 
-    std::fs::read_to_string(filename)
-        .unwrap()
-        .lines()
-        .for_each(|line| {
-            // We collect the coordinate pairs into a vector.
-            let coords = line
-                .split(" -> ")
-                .map(|coords| {
-                    let coords = coords.split(",").collect::<Vec<_>>();
-                    let x = coords.first().unwrap().parse::<u16>().unwrap();
-                    let y = coords.last().unwrap().parse::<u16>().unwrap();
-
-                    (x, y)
-                })
-                .collect::<Vec<_>>();
-
-            // We take the starting coordinates for the firs rock formation.
-            let (mut first_x, mut first_y) = coords.first().unwrap().clone();
-
-            // Then we iterate through the rest of the coordinates.
-            for (x, y) in coords.iter().skip(1) {
-                // If there is a change in the `x` coordinate, we iterate over the range of changes
-                // and insert a rock item into our map.
-                match first_x.cmp(x) {
-                    std::cmp::Ordering::Less => {
-                        for current_x in first_x..*x + 1 {
-                            map.insert((current_x, *y), Item::Rock);
-                        }
+    input.lines().for_each(|line| {
+        // We collect the coordinate pairs into a vector.
+        let coords = line
+            .split(" -> ")
+            .map(|coords| {
+                let coords = coords.split(",").collect::<Vec<_>>();
+                let x = coords.first().unwrap().parse::<u16>().unwrap();
+                let y = coords.last().unwrap().parse::<u16>().unwrap();
+
+                (x, y)
+            })
+            .collect::<Vec<_>>();
+
+        // We take the starting coordinates for the firs rock formation.
+        let (mut first_x, mut first_y) = *coords.first().unwrap();
+
+        // Then we iterate through the rest of the coordinates.
+        for (x, y) in coords.iter().skip(1) {
+            // If there is a change in the `x` coordinate, we iterate over the range of changes
+            // and insert a rock item into our map.
+            match first_x.cmp(x) {
+                std::cmp::Ordering::Less => {
+                    for current_x in first_x..*x + 1 {
+                        map.insert((current_x, *y), Item::Rock);
                     }
-                    std::cmp::Ordering::Equal => {}
-                    std::cmp::Ordering::Greater => {
-                        for current_x in *x..first_x + 1 {
-                            map.insert((current_x, *y), Item::Rock);
-                        }
+                }
+                std::cmp::Ordering::Equal => {}
+                std::cmp::Ordering::Greater => {
+                    for current_x in *x..first_x + 1 {
+                        map.insert((current_x, *y), Item::Rock);
                     }
                 }
+            }
 
-                // Similarly to a change in `x`, we also record any changes in the `y` coordinate.
-                match first_y.cmp(y) {
-                    std::cmp::Ordering::Less => {
-                        for current_y in first_y..*y + 1 {
-                            map.insert((*x, current_y), Item::Rock);
-                        }
+            // Similarly to a change in `x`, we also record any changes in the `y` coordinate.
+            match first_y.cmp(y) {
+                std::cmp::Ordering::Less => {
+                    for current_y in first_y..*y + 1 {
+                        map.insert((*x, current_y), Item::Rock);
                     }
-                    std::cmp::Ordering::Equal => {}
-                    std::cmp::Ordering::Greater => {
-                        for current_y in *y..first_y + 1 {
-                            map.insert((*x, current_y), Item::Rock);
-                        }
+                }
+                std::cmp::Ordering::Equal => {}
+                std::cmp::Ordering::Greater => {
+                    for current_y in *y..first_y + 1 {
+                        map.insert((*x, current_y), Item::Rock);
                     }
                 }
+            }
 
-                // We change the current x and y coordinates to be the next iterations starting
-                // coordinates.
-                first_x = *x;
-                first_y = *y;
+            // We change the current x and y coordinates to be the next iterations starting
+            // coordinates.
+            first_x = *x;
+            first_y = *y;
 
-                // If this is the heighest point we have seen yet, we record it into our height
-                // variable. Otherwise we move on.
-                if first_y > height {
-                    height = first_y;
-                }
+            // If this is the heighest point we have seen yet, we record it into our height
+            // variable. Otherwise we move on.
+            if first_y > height {
+                height = first_y;
             }
-        });
+        }
+    });
 
     (map, height)
 }
 
-/// Drop a single drop of sand until it stops moving because of a rock or a peice of sand.
-/// If we can't move the drop of sand any further we return false, but if we found a place for
-/// this drop of sand we record it in the map and return true.
-fn drop_sand(map: &mut HashMap<(u16, u16), Item>, height: &u16) -> bool {
+/// Read the rock locations from the input file, along with the height of the cave.
+fn get_rock_locations(filename: &str) -> (HashMap<(u16, u16), Item>, u16) {
+    parse_rock_locations(&read_input(filename).unwrap())
+}
+
+/// Get the coordinates a drop of sand tries to fall to in order of preference - down, down and
+/// left, and down and right. Coordinates which would leave the range of a `u16` are skipped.
+fn fall_targets((x, y): (u16, u16)) -> impl Iterator<Item = (u16, u16)> {
+    let below = y.checked_add(1);
+
+    [Some(x), x.checked_sub(1), x.checked_add(1)]
+        .into_iter()
+        .filter_map(move |x| Some((x?, below?)))
+}
+
+/// An enum representing where a grain of sand ended up after it was dropped.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum Rest {
+    /// The grain came to rest at the given coordinates.
+    Settled((u16, u16)),
+    /// The grain fell past the lowest rock into the abyss.
+    FellOut,
+    /// The source of the sand is blocked, so the grain couldn't be dropped.
+    BlockedSource,
+}
+
+/// Drop exactly one grain of sand until it stops moving because of a rock or a peice of sand.
+/// If the cave has a floor, it is `floor_offset` units below the lowest rock and stops the sand
+/// from falling any further.
+/// If the grain found a place we record it in the map, and in any case return where it ended up.
+/// Returns an error if the floor would be too deep for the coordinates of the cave.
+fn add_one_grain(
+    map: &mut HashMap<(u16, u16), Item>,
+    height: &u16,
+    floor_offset: Option<u16>,
+) -> Result<Rest, String> {
     let (mut start_x, mut start_y) = (500, 0);
+    let floor = match floor_offset {
+        Some(offset) => Some(height.checked_add(offset).ok_or_else(|| {
+            format!("A floor {offset} units below a cave of height {height} is too deep")
+        })?),
+        None => None,
+    };
+
+    // If the source is already taken or in the floor, the sand can't go anywhere.
+    if map.contains_key(&(start_x, start_y)) || floor.is_some_and(|floor| start_y >= floor) {
+        return Ok(Rest::BlockedSource);
+    }
 
     loop {
-        // If we are exceeding the height of the map we cannot move the sand any more.
-        if start_y >= *height {
-            return false;
+        // If there is no floor and we are exceeding the height of the map the sand falls out.
+        if floor.is_none() && start_y >= *height {
+            return Ok(Rest::FellOut);
         }
 
-        // If there is no item below, we move down.
-        if !map.contains_key(&(start_x, start_y + 1)) {
-            start_y += 1;
-            continue;
-        }
+        // If the sand is right above the floor it can't fall any further.
+        let on_floor = floor.is_some_and(|floor| start_y + 1 >= floor);
 
-        // If there was an item below, but not down and left, we go there.
-        if !map.contains_key(&(start_x - 1, start_y + 1)) {
-            start_x -= 1;
-            start_y += 1;
+        // If there is an open spot below, down and left or down and right (in that order), we
+        // move there.
+        if let Some(next) =
+            fall_targets((start_x, start_y)).find(|coords| !on_floor && !map.contains_key(coords))
+        {
+            (start_x, start_y) = next;
             continue;
         }
 
-        // If both down and down left were taken, but not down right we go down right.
-        if !map.contains_key(&(start_x + 1, start_y + 1)) {
-            start_x += 1;
-            start_y += 1;
-            continue;
-        }
+        // The sand drop cannot go anywhere, so we put it here. The location is open since the
+        // sand only ever moves to open locations.
+        map.insert((start_x, start_y), Item::Sand);
 
-        // The sand drop cannot go anywhere, but the current location is open, so we put it here
-        // and return true.
-        if !map.contains_key(&(start_x, start_y)) {
-            map.insert((start_x, start_y), Item::Sand);
-            return true;
-        }
+        return Ok(Rest::Settled((start_x, start_y)));
+    }
+}
+
+/// Drop sand until no more sand can be and count the units of sand in the cave. If
+/// `floor_offset` is given, the cave has a floor that many units below the lowest rock.
+fn count_sand(
+    map: &mut HashMap<(u16, u16), Item>,
+    height: &u16,
+    floor_offset: Option<u16>,
+) -> Result<usize, String> {
+    while let Rest::Settled(_) = add_one_grain(map, height, floor_offset)? {}
+
+    Ok(map.values().filter(|item| item == &&Item::Sand).count())
+}
+
+/// The corners of an area of the cave as the minimum and maximum coordinates.
+type Bounds = ((u16, u16), (u16, u16));
+
+/// Convert the sparse map of the cave into a dense grid covering the given bounds, with a row for
+/// each y and a cell for each x. Items outside of the bounds are left out.
+fn to_dense(
+    map: &HashMap<(u16, u16), Item>,
+    ((min_x, min_y), (max_x, max_y)): Bounds,
+) -> Vec<Vec<Option<Item>>> {
+    (min_y..=max_y)
+        .map(|y| (min_x..=max_x).map(|x| map.get(&(x, y)).cloned()).collect())
+        .collect()
+}
+
+/// Convert a dense grid of the cave, whose top left cell is at `(min_x, min_y)`, back into a
+/// sparse map holding only the occupied cells.
+fn from_dense(grid: &[Vec<Option<Item>>], (min_x, min_y): (u16, u16)) -> HashMap<(u16, u16), Item> {
+    grid.iter()
+        .zip(min_y..)
+        .flat_map(|(row, y)| {
+            row.iter()
+                .zip(min_x..)
+                .filter_map(move |(cell, x)| cell.clone().map(|item| ((x, y), item)))
+        })
+        .collect()
+}
+
+/// Render a dense grid of the cave, whose top left cell is at `(min_x, min_y)`, into a String
+/// with `#` for rocks, `o` for sand and `+` for the source of the sand.
+fn render_dense(grid: &[Vec<Option<Item>>], (min_x, min_y): (u16, u16)) -> String {
+    grid.iter()
+        .zip(min_y..)
+        .map(|(row, y)| {
+            row.iter()
+                .zip(min_x..)
+                .map(|(cell, x)| match cell {
+                    Some(Item::Rock) => '#',
+                    Some(Item::Sand) => 'o',
+                    None if (x, y) == (500, 0) => '+',
+                    None => '.',
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Get the bounds of the area around the items of the cave and the source of the sand.
+fn cave_bounds(map: &HashMap<(u16, u16), Item>) -> Bounds {
+    let ((min_x, min_y), (max_x, max_y)) = bounding_box(
+        map.keys()
+            .chain([(500, 0)].iter())
+            .map(|(x, y)| (*x as i64, *y as i64)),
+    )
+    .unwrap();
+
+    ((min_x as u16, min_y as u16), (max_x as u16, max_y as u16))
+}
 
-        // We cannot go anywhere and the location is taken, so no more sand can go here.
-        return false;
+/// A representation of the cave, so that rendering doesn't depend on how the cave is stored.
+trait Cave {
+    /// Get the occupied cells of the cave as a sparse map.
+    fn to_sparse(&self) -> Cow<'_, HashMap<(u16, u16), Item>>;
+}
+
+impl Cave for HashMap<(u16, u16), Item> {
+    fn to_sparse(&self) -> Cow<'_, HashMap<(u16, u16), Item>> {
+        Cow::Borrowed(self)
     }
 }
 
-/// Drop sand until no more sand can be.
-fn drop_all_sand(map: &mut HashMap<(u16, u16), Item>, height: &u16) {
-    while drop_sand(map, height) {}
+/// A dense grid of the cave along with the coordinates of its top left cell.
+impl Cave for (Vec<Vec<Option<Item>>>, (u16, u16)) {
+    fn to_sparse(&self) -> Cow<'_, HashMap<(u16, u16), Item>> {
+        Cow::Owned(from_dense(&self.0, self.1))
+    }
+}
+
+/// Render the cave into a String with `#` for rocks, `o` for sand and `+` for the source of
+/// the sand. Only the area around the items and the source is rendered, whichever
+/// representation the cave is in.
+fn render_cave(cave: &impl Cave) -> String {
+    let map = cave.to_sparse();
+    let bounds = cave_bounds(&map);
+
+    render_dense(&to_dense(&map, bounds), bounds.0)
+}
+
+/// Export the cave within the given bounds as an SVG image with a gray square for every rock and
+/// a yellow square for every grain of sand. The view box covers the bounds in cave coordinates,
+/// and like the cave, the y axis of an SVG grows downwards.
+fn to_svg(map: &HashMap<(u16, u16), Item>, ((min_x, min_y), (max_x, max_y)): Bounds) -> String {
+    // Sort the items by row and column so that the output doesn't depend on the map's order.
+    let mut items = map
+        .iter()
+        .filter(|((x, y), _)| (min_x..=max_x).contains(x) && (min_y..=max_y).contains(y))
+        .collect::<Vec<_>>();
+    items.sort_by_key(|((x, y), _)| (*y, *x));
+
+    let rects = items
+        .into_iter()
+        .map(|((x, y), item)| {
+            let fill = match item {
+                Item::Rock => "#808080",
+                Item::Sand => "#e0c060",
+            };
+
+            format!("<rect x=\"{x}\" y=\"{y}\" width=\"1\" height=\"1\" fill=\"{fill}\"/>")
+        })
+        .collect::<Vec<_>>();
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{min_x} {min_y} {} {}\" \
+         shape-rendering=\"crispEdges\">\n{}\n</svg>\n",
+        max_x - min_x + 1,
+        max_y - min_y + 1,
+        rects.join("\n")
+    )
 }
 
 fn main() {
     // Get the cave layout and height.
-    let (mut map, height) = get_rock_locations("input.txt");
+    let (mut map, height) = get_rock_locations(input_filename());
 
-    // Drop sand into the cave.
-    drop_all_sand(&mut map, &height);
+    // Print where each grain of sand comes to rest, one grain at a time, if requested.
+    if extra_output("--grains") {
+        let mut grains_map = map.clone();
+        let mut grain = 1;
 
-    // Count the number of sand drops in the cave.
-    let sand_units = map.iter().filter(|(_, item)| item == &&Item::Sand).count();
+        loop {
+            match add_one_grain(&mut grains_map, &height, None) {
+                Ok(Rest::Settled((x, y))) => println!("Grain {grain} came to rest at {x},{y}"),
+                Ok(rest) => {
+                    println!("Grain {grain}: {rest:?}\n");
+                    break;
+                }
+                Err(error) => {
+                    eprintln!("{error}");
+                    std::process::exit(1);
+                }
+            }
 
-    // Add a floor to the cave.
-    (0..std::u16::MAX).for_each(|x| {
-        map.insert((x, height + 2), Item::Rock);
-    });
+            grain += 1;
+        }
+    }
 
-    // Drop more sand into the cave.
-    drop_all_sand(&mut map, &(height + 2));
+    // Drop sand into the cave and count the number of sand drops in the cave.
+    let sand_units = match count_sand(&mut map, &height, None) {
+        Ok(sand_units) => sand_units,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
 
-    // Count the number of sand drops in the cave again.
-    let second_sand_units = map.iter().filter(|(_, item)| item == &&Item::Sand).count();
+    // Render the cave if requested.
+    if extra_output("--render") {
+        println!("{}\n", render_cave(&map));
+    }
 
-    println!("{sand_units}");
-    println!("{second_sand_units}");
+    // Export the cave to `cave.svg` if requested.
+    if std::env::args().any(|arg| arg == "--svg") {
+        if let Err(error) = std::fs::write("cave.svg", to_svg(&map, cave_bounds(&map))) {
+            eprintln!("Couldn't write cave.svg: {error}");
+            std::process::exit(1);
+        }
+    }
+
+    // The floor is two units below the lowest rock, unless a different offset is requested.
+    let floor_offset = match parse_flag("--floor-offset", 2) {
+        Ok(floor_offset) => floor_offset,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+
+    // Drop more sand into the cave now that it has a floor and count the number of sand drops in
+    // the cave again.
+    let second_sand_units = match count_sand(&mut map, &height, Some(floor_offset)) {
+        Ok(sand_units) => sand_units,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+
+    print_answers(14, sand_units, second_sand_units);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "498,4 -> 498,6 -> 496,6\n503,4 -> 502,4 -> 502,9 -> 494,9\n";
+
+    #[test]
+    fn counts_the_example_sand_with_and_without_a_floor() {
+        let (map, height) = parse_rock_locations(EXAMPLE);
+
+        assert_eq!(count_sand(&mut map.clone(), &height, None), Ok(24));
+        assert_eq!(count_sand(&mut map.clone(), &height, Some(2)), Ok(93));
+    }
+
+    #[test]
+    fn adding_grains_one_at_a_time_matches_the_batch() {
+        let (map, height) = parse_rock_locations(EXAMPLE);
+
+        for floor_offset in [None, Some(2)] {
+            let mut grains_map = map.clone();
+            let mut grains = 0;
+
+            while let Rest::Settled(_) =
+                add_one_grain(&mut grains_map, &height, floor_offset).unwrap()
+            {
+                grains += 1;
+            }
+
+            assert_eq!(
+                Ok(grains),
+                count_sand(&mut map.clone(), &height, floor_offset)
+            );
+        }
+    }
+
+    #[test]
+    fn blocked_source_stops_right_away() {
+        let (mut map, height) = parse_rock_locations(EXAMPLE);
+        count_sand(&mut map, &height, Some(2)).unwrap();
+        let filled = map.clone();
+
+        assert_eq!(
+            add_one_grain(&mut map, &height, Some(2)),
+            Ok(Rest::BlockedSource)
+        );
+        assert_eq!(map, filled);
+    }
+
+    #[test]
+    fn floor_offset_changes_the_pile() {
+        let (map, height) = parse_rock_locations(EXAMPLE);
+
+        let with_offset = |offset| count_sand(&mut map.clone(), &height, Some(offset)).unwrap();
+
+        assert_eq!(with_offset(2), 93);
+        assert!(with_offset(3) > 93);
+        // A floor at the lowest rock doesn't panic either.
+        assert!(with_offset(0) > 0);
+    }
+
+    #[test]
+    fn too_deep_floor_is_an_error() {
+        let (mut map, height) = parse_rock_locations(EXAMPLE);
+
+        assert!(count_sand(&mut map, &height, Some(u16::MAX)).is_err());
+    }
+
+    #[test]
+    fn fall_targets_stay_in_range() {
+        // A grain at the left edge can only fall down or down and right.
+        assert_eq!(fall_targets((0, 1)).collect::<Vec<_>>(), [(0, 2), (1, 2)]);
+        assert_eq!(
+            fall_targets((u16::MAX, 1)).collect::<Vec<_>>(),
+            [(u16::MAX, 2), (u16::MAX - 1, 2)]
+        );
+        assert_eq!(fall_targets((500, u16::MAX)).count(), 0);
+    }
+
+    #[test]
+    fn svg_has_a_rect_for_each_item_in_bounds() {
+        let map = HashMap::from([
+            ((498, 4), Item::Rock),
+            ((499, 4), Item::Rock),
+            ((500, 4), Item::Rock),
+            ((499, 3), Item::Sand),
+            ((510, 4), Item::Rock),
+        ]);
+
+        let svg = to_svg(&map, ((495, 0), (505, 4)));
+
+        assert_eq!(svg.matches("<rect").count(), 4);
+        assert_eq!(svg.matches("fill=\"#e0c060\"").count(), 1);
+        assert!(svg.contains("viewBox=\"495 0 11 5\""));
+        // The y axis grows downwards in both the cave and the SVG.
+        assert!(svg.contains("<rect x=\"499\" y=\"3\""));
+    }
+
+    #[test]
+    fn cave_round_trips_through_a_dense_grid() {
+        let map = HashMap::from([
+            ((498, 4), Item::Rock),
+            ((498, 5), Item::Rock),
+            ((498, 6), Item::Rock),
+            ((497, 6), Item::Rock),
+            ((500, 8), Item::Sand),
+            ((499, 8), Item::Sand),
+            ((500, 7), Item::Sand),
+        ]);
+        let bounds = cave_bounds(&map);
+
+        let grid = to_dense(&map, bounds);
+
+        assert_eq!(bounds, ((497, 0), (500, 8)));
+        assert_eq!(grid.len(), 9);
+        assert!(grid.iter().all(|row| row.len() == 4));
+        assert_eq!(grid[4][1], Some(Item::Rock));
+        assert_eq!(grid[8][3], Some(Item::Sand));
+        assert_eq!(grid[0][3], None);
+        assert_eq!(from_dense(&grid, bounds.0), map);
+    }
+
+    #[test]
+    fn both_representations_render_the_same() {
+        let map = HashMap::from([
+            ((498, 4), Item::Rock),
+            ((498, 5), Item::Rock),
+            ((497, 5), Item::Rock),
+            ((500, 5), Item::Sand),
+        ]);
+        // Leave a margin around the cave to check the dense grid is cropped to the same area.
+        let grid = to_dense(&map, ((495, 0), (502, 7)));
+        let expected = ["...+", "....", "....", "....", ".#..", "##.o"].join("\n");
+
+        assert_eq!(render_cave(&map), expected);
+        assert_eq!(render_cave(&(grid, (495, 0))), expected);
+    }
 }
@@ -0,0 +1,32 @@
+use std::process::{Command, Output};
+
+/// Run the solution against the sample with the given `--floor-offset` value.
+fn floor_offset(value: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_day-14"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["--example", "--floor-offset", value])
+        .output()
+        .expect("Couldn't run day-14")
+}
+
+#[test]
+fn a_lower_floor_holds_more_sand() {
+    let output = floor_offset("3");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "24\n111\n");
+}
+
+#[test]
+fn rejects_invalid_offsets() {
+    for value in ["x", "-1"] {
+        let output = floor_offset(value);
+
+        assert_eq!(output.status.code(), Some(1));
+        assert!(output.stdout.is_empty());
+        assert_eq!(
+            String::from_utf8(output.stderr).unwrap(),
+            format!("Invalid value \"{value}\" for --floor-offset\n")
+        );
+    }
+}
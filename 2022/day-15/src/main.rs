@@ -1,99 +1,151 @@
-use std::{collections::BTreeMap, ops::RangeInclusive};
-
-/// A struct that represents a point on the map. It could be a sensor, a beacon or a point which a
-/// sensor covers, in this case named Nothing.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Item {
-    Sensor,
-    Beacon,
-    Nothing,
-}
+use std::{collections::BTreeSet, ops::RangeInclusive};
+
+use aoc_common::{
+    answers_only, extra_output, flag_value, input_filename, merge_intervals, parse_flag,
+    print_answers, read_input, report_error, verbose, ParseError,
+};
+
+/// A point on the map as its x and y coordinates.
+type Point = (i32, i32);
 
 /// Read the coordinates from a string in the one of the following formats:
 /// Sensor at x=20, y=1
 /// closest beacon is at x=15, y=3
-fn read_coords(instruction: &str) -> (i32, i32) {
-    let from_x = instruction
-        .get(instruction.find("x=").unwrap() + 2..)
-        .unwrap();
-    let split = from_x.split(",").collect::<Vec<_>>();
-    let x = split.first().unwrap().parse().unwrap();
-    let y = split.last().unwrap().get(3..).unwrap().parse().unwrap();
-
-    (x, y)
+fn read_coords(instruction: &str) -> Result<Point, String> {
+    let (_, coords) = instruction
+        .split_once("x=")
+        .ok_or_else(|| format!("Missing x coordinate in \"{instruction}\""))?;
+    let (x, y) = coords
+        .split_once(", y=")
+        .ok_or_else(|| format!("Missing y coordinate in \"{instruction}\""))?;
+    let parse = |coord: &str| {
+        coord
+            .parse::<i32>()
+            .map_err(|error| format!("Invalid coordinate \"{coord}\": {error}"))
+    };
+
+    Ok((parse(x)?, parse(y)?))
 }
 
-/// Get the range of coordinates a sensor would cover at a target y based on the beacon closest to
-/// the sensor.
-fn get_empty_coords(sensor: &(i32, i32), beacon: &(i32, i32), target_y: i32) -> Option<(i32, i32)> {
-    let distance = (sensor.0.abs_diff(beacon.0) + sensor.1.abs_diff(beacon.1)) as i32;
+/// Get the range of x coordinates a sensor would cover at a target y based on the beacon closest to
+/// the sensor. The sensor covers every point no further from it than the beacon, so the range
+/// shrinks the further the target y is from the sensor.
+fn coverage_at(sensor: &Point, beacon: &Point, y: i32) -> Option<RangeInclusive<i64>> {
+    let distance = (sensor.0.abs_diff(beacon.0) as i64) + (sensor.1.abs_diff(beacon.1) as i64);
 
-    let y_distance = sensor.1.abs_diff(target_y) as i32;
+    let y_distance = sensor.1.abs_diff(y) as i64;
+    let x = i64::from(sensor.0);
 
     if y_distance > distance {
         None
     } else {
-        Some((
-            sensor.0 - (distance - y_distance),
-            sensor.0 + (distance - y_distance),
-        ))
+        Some((x - (distance - y_distance))..=(x + (distance - y_distance)))
     }
 }
 
-/// Read the instructions from the input file for a target y into a BTreeMap.
-fn read_map(filename: &str, target_y: i32) -> BTreeMap<i32, Item> {
-    let mut map = BTreeMap::<i32, Item>::new();
-
-    std::fs::read_to_string(filename)
-        .unwrap()
+/// Parse the sensors and their closest beacons, one pair per line. Returns an error naming the
+/// offending line if a line can't be parsed.
+fn parse_sensors(input: &str) -> Result<Vec<(Point, Point)>, ParseError> {
+    input
         .lines()
-        .for_each(|line| {
-            let split = line.split(":").collect::<Vec<_>>();
-            let sensor = read_coords(split.first().unwrap());
-            let beacon = read_coords(split.last().unwrap());
+        .enumerate()
+        .map(|(index, line)| {
+            let (sensor, beacon) = line
+                .split_once(":")
+                .ok_or_else(|| ParseError::new(index + 1, "missing beacon"))?;
+            let with_line = |error| ParseError::new(index + 1, error);
 
-            if let Some((min, max)) = get_empty_coords(&sensor, &beacon, target_y) {
-                map.extend((min..=max).map(|x| (x, Item::Nothing)));
-            }
+            Ok((
+                read_coords(sensor).map_err(with_line)?,
+                read_coords(beacon).map_err(with_line)?,
+            ))
+        })
+        .collect()
+}
 
-            if sensor.1 == target_y {
-                map.insert(sensor.0, Item::Sensor);
-            }
+/// Get the coverage of all the sensors at a target y as sorted, disjoint ranges.
+fn merged_coverage(sensors: &[(Point, Point)], y: i32) -> Vec<RangeInclusive<i64>> {
+    let mut ranges = sensors
+        .iter()
+        .filter_map(|(sensor, beacon)| coverage_at(sensor, beacon, y))
+        .collect::<Vec<_>>();
 
-            if beacon.1 == target_y {
-                map.insert(beacon.0, Item::Beacon);
-            }
-        });
+    merge_intervals(&mut ranges)
+}
+
+/// Count the positions at a target y which are covered by the sensors, leaving out the positions
+/// of the sensors and beacons themselves. Rows no sensor reaches have no coverage and count 0, and
+/// gaps between the merged ranges aren't counted.
+fn count_covered(sensors: &[(Point, Point)], y: i32) -> usize {
+    let coverage = merged_coverage(sensors, y);
+
+    let covered = coverage
+        .iter()
+        .map(|range| (range.end() - range.start() + 1) as usize)
+        .sum::<usize>();
 
-    map
+    // Sensors and beacons always lie in the coverage of their own sensor.
+    let occupied = sensors
+        .iter()
+        .flat_map(|(sensor, beacon)| [sensor, beacon])
+        .filter(|(_, item_y)| *item_y == y)
+        .map(|(x, _)| x)
+        .collect::<BTreeSet<_>>()
+        .len();
+
+    covered - occupied
+}
+
+/// The largest bound `render_region` will draw.
+const MAX_RENDER_BOUND: i32 = 100;
+
+/// Render the region from 0 to and including `bound` on both axes with `S` for sensors, `B` for
+/// beacons, `#` for the positions covered by the sensors and `.` for the rest. Only small regions
+/// like the example's can be drawn, so larger bounds return an error.
+fn render_region(sensors: &[(Point, Point)], bound: i32) -> Result<String, String> {
+    if !(0..=MAX_RENDER_BOUND).contains(&bound) {
+        return Err(format!(
+            "Can only render bounds from 0 to {MAX_RENDER_BOUND}, got {bound}"
+        ));
+    }
+
+    Ok((0..=bound)
+        .map(|y| {
+            let coverage = merged_coverage(sensors, y);
+
+            (0..=bound)
+                .map(|x| {
+                    if sensors.iter().any(|(sensor, _)| sensor == &(x, y)) {
+                        'S'
+                    } else if sensors.iter().any(|(_, beacon)| beacon == &(x, y)) {
+                        'B'
+                    } else if coverage.iter().any(|range| range.contains(&i64::from(x))) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
 }
 
-/// Read all of the sensor coverages from the input file into a vector of vector ranges.
+/// Get all of the sensor coverages as a vector of vector ranges.
 /// Sort the vector ranges based on the y they cover and the start of the range for x.
 /// Fold the ranges into a vector of vectors disregarding the y coordinate and filtering any which
-/// would belong to the y coordinates outside the range 0 to and including T.
-fn read_full_map<const T: i32>(filename: &str) -> Vec<Vec<RangeInclusive<i32>>> {
+/// would belong to the y coordinates outside the range 0 to and including `end`.
+fn full_map(sensors: &[(Point, Point)], end: i32) -> Vec<Vec<RangeInclusive<i64>>> {
     // Get all the ranges for each sensor.
-    let mut ranges = std::fs::read_to_string(filename)
-        .unwrap()
-        .lines()
-        .flat_map(|line| {
-            let split = line.split(":").collect::<Vec<_>>();
-            let sensor = read_coords(split.first().unwrap());
-            let (x, y) = read_coords(split.last().unwrap());
-
+    let mut ranges = sensors
+        .iter()
+        .flat_map(|&(sensor, beacon)| {
             // Calculate the sensor area distance based on closes beacon.
-            let distance = (sensor.0 - x).abs() + (sensor.1 - y).abs();
+            let distance = (sensor.0 - beacon.0).abs() + (sensor.1 - beacon.1).abs();
 
             // Calcluate the sensors areas for each y.
             ((sensor.1 - distance)..=(sensor.1 + distance))
-                .map(|y| {
-                    (
-                        y,
-                        (sensor.0 - (distance - (sensor.1 - y).abs()))
-                            ..=(sensor.0 + (distance - (sensor.1 - y).abs())),
-                    )
-                })
+                .filter_map(|y| Some((y, coverage_at(&sensor, &beacon, y)?)))
                 .collect::<Vec<_>>()
         })
         .collect::<Vec<_>>();
@@ -113,7 +165,7 @@ fn read_full_map<const T: i32>(filename: &str) -> Vec<Vec<RangeInclusive<i32>>>
     ranges
         .iter()
         .fold(
-            Vec::<(i32, Vec<RangeInclusive<i32>>)>::new(),
+            Vec::<(i32, Vec<RangeInclusive<i64>>)>::new(),
             |mut acc, curr| {
                 if acc.is_empty() {
                     acc.push((curr.0, vec![curr.1.clone()]));
@@ -135,52 +187,302 @@ fn read_full_map<const T: i32>(filename: &str) -> Vec<Vec<RangeInclusive<i32>>>
             },
         )
         .into_iter()
-        .filter(|(y, _)| (0..=T).contains(y))
+        .filter(|(y, _)| (0..=end).contains(y))
         .map(|(_, ranges)| ranges)
         .collect()
 }
 
-fn main() {
-    // Specify the target y to check for.
-    let target_y = 2_000_000;
-    // Read the map from the input file.
-    let map = read_map("input.txt", target_y);
-    // Count how many sensor fields are covered.
-    let count_empty = map
+/// Count the positions at a target y covered by the sensors by checking every x within reach of a
+/// sensor one at a time. This is the slow reference `--validate-answers` checks the answer to the
+/// first part against.
+fn part1_reference(sensors: &[(Point, Point)], y: i32) -> usize {
+    let distance = |a: &Point, b: &Point| (a.0.abs_diff(b.0) + a.1.abs_diff(b.1)) as i32;
+    let reaches = sensors
         .iter()
-        .filter(|(_, &item)| item == Item::Nothing)
-        .count();
+        .map(|(sensor, beacon)| (sensor.0, distance(sensor, beacon)))
+        .collect::<Vec<_>>();
 
-    println!("{count_empty}");
+    let min_x = reaches
+        .iter()
+        .map(|(x, reach)| x - reach)
+        .min()
+        .unwrap_or(0);
+    let max_x = reaches
+        .iter()
+        .map(|(x, reach)| x + reach)
+        .max()
+        .unwrap_or(-1);
 
-    // Specify the end of the range.
-    const END: i32 = 4_000_000;
+    (min_x..=max_x)
+        .filter(|x| {
+            let point = (*x, y);
 
-    // Read the map of ranges.
-    let full_map = read_full_map::<END>("input.txt");
+            sensors
+                .iter()
+                .all(|(sensor, beacon)| sensor != &point && beacon != &point)
+                && sensors
+                    .iter()
+                    .any(|(sensor, beacon)| distance(sensor, &point) <= distance(sensor, beacon))
+        })
+        .count()
+}
 
-    // Get the x and y not covered by any sensor.
-    let (x, y) = full_map
-        .iter()
+/// Find the tuning frequency of the distress beacon by checking every position just out of reach
+/// of a sensor within the range 0 to and including `end`. The beacon is the only uncovered
+/// position, so it has to lie right next to the edge of some sensor's reach. This is the slow
+/// reference `--validate-answers` checks the answer to the second part against.
+fn part2_reference(sensors: &[(Point, Point)], end: i32) -> Option<usize> {
+    let distance = |a: &Point, b: &Point| (a.0.abs_diff(b.0) + a.1.abs_diff(b.1)) as i32;
+    let covered = |point: &Point| {
+        sensors
+            .iter()
+            .any(|(sensor, beacon)| distance(sensor, point) <= distance(sensor, beacon))
+    };
+
+    sensors.iter().find_map(|(sensor, beacon)| {
+        let reach = distance(sensor, beacon) + 1;
+
+        (0..=reach)
+            .flat_map(|dx| {
+                let dy = reach - dx;
+
+                [(dx, dy), (dx, -dy), (-dx, dy), (-dx, -dy)]
+            })
+            .map(|(dx, dy)| (sensor.0 + dx, sensor.1 + dy))
+            .filter(|(x, y)| (0..=end).contains(x) && (0..=end).contains(y))
+            .find(|point| !covered(point))
+            .map(tuning_frequency)
+    })
+}
+
+/// Find the distress beacon - the only position within the range 0 to and including `end` on both
+/// axes which isn't covered by any sensor. The rows are scanned from 0 onwards and the search
+/// stops at the first row with a gap, so the number of rows scanned is returned with the beacon.
+fn find_beacon(sensors: &[(Point, Point)], end: i32) -> Option<(Point, usize)> {
+    full_map(sensors, end)
+        .iter_mut()
         .enumerate()
         .find_map(|(y, ranges)| {
-            let mut start_range = 0..=0;
-
-            for current_range in ranges {
-                if start_range.end() + 1 >= *current_range.start() {
-                    start_range =
-                        *start_range.start()..=(*current_range.end().max(start_range.end()));
-                } else if start_range.end() > &END {
-                    return None;
-                } else {
-                    return Some(((start_range.end() + 1) as usize, y));
+            // Find the first x from 0 onwards which isn't covered by the merged ranges.
+            let mut x = 0i64;
+
+            for range in merge_intervals(ranges) {
+                if *range.start() > x {
+                    break;
                 }
+
+                x = x.max(range.end() + 1);
             }
 
-            return None;
+            (x <= i64::from(end)).then_some(((x as i32, y as i32), y + 1))
         })
-        .unwrap();
+}
+
+/// Calculate the tuning frequency of the distress beacon.
+fn tuning_frequency((x, y): Point) -> usize {
+    x as usize * 4_000_000 + y as usize
+}
+
+fn main() {
+    // The example uses a smaller region than the puzzle input.
+    let example = std::env::args().any(|arg| arg == "--example");
 
-    println!("{x},{y}");
-    println!("{}", x * (END as usize) + y);
+    // Specify the target y to check for, unless a different row is requested.
+    let target_y = match parse_flag("--row", if example { 10 } else { 2_000_000 }) {
+        Ok(row) => row,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    // Read the sensors from the input file.
+    let input = match read_input(input_filename()) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    let sensors = match parse_sensors(&input) {
+        Ok(sensors) => sensors,
+        Err(error) => {
+            eprintln!("{}", report_error(&error, &input, verbose()));
+            std::process::exit(1);
+        }
+    };
+
+    // Render the region the distress beacon could be in if requested, up to the given bound.
+    if let Some(bound) = flag_value("--render").filter(|_| !answers_only()) {
+        let region = bound
+            .map_or(Ok(20), |bound| {
+                bound
+                    .parse()
+                    .map_err(|_| format!("Invalid value \"{bound}\" for --render"))
+            })
+            .and_then(|bound| render_region(&sensors, bound));
+
+        match region {
+            Ok(region) => println!("{region}\n"),
+            Err(error) => {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Count how many sensor fields are covered.
+    let count_empty = count_covered(&sensors, target_y);
+
+    // Specify the end of the range the distress beacon is in.
+    let end = if example { 20 } else { 4_000_000 };
+
+    // Find the position not covered by any sensor.
+    let Some((beacon, rows_scanned)) = find_beacon(&sensors, end) else {
+        eprintln!("Every position from 0 to {end} is covered by a sensor");
+        std::process::exit(1);
+    };
+
+    // Print the distress beacon and how many rows were scanned to find it if requested.
+    if extra_output("--beacon") {
+        println!(
+            "Distress beacon at {},{} after scanning {rows_scanned} rows\n",
+            beacon.0, beacon.1
+        );
+    }
+
+    let tuning_frequency = tuning_frequency(beacon);
+
+    // Check the answers against the slow reference implementations if requested.
+    if std::env::args().any(|arg| arg == "--validate-answers") {
+        let reference = (
+            part1_reference(&sensors, target_y),
+            part2_reference(&sensors, end),
+        );
+
+        if reference != (count_empty, Some(tuning_frequency)) {
+            eprintln!(
+                "Answers {count_empty} and {tuning_frequency} don't match the reference answers {} and {:?}",
+                reference.0, reference.1
+            );
+            std::process::exit(1);
+        }
+
+        if !answers_only() {
+            eprintln!("Answers match the reference implementation.");
+        }
+    }
+
+    print_answers(15, count_empty, tuning_frequency);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = include_str!("../sample.txt");
+
+    #[test]
+    fn answers_match_the_reference() {
+        // The puzzle input is too large to check every position of in a test, so only the
+        // example is checked here and the puzzle input is left to `--validate-answers`.
+        let sensors = parse_sensors(EXAMPLE).unwrap();
+
+        for y in -15..=35 {
+            assert_eq!(
+                count_covered(&sensors, y),
+                part1_reference(&sensors, y),
+                "row {y}"
+            );
+        }
+
+        let beacon = find_beacon(&sensors, 20).map(|(beacon, _)| tuning_frequency(beacon));
+        assert_eq!(beacon, part2_reference(&sensors, 20));
+        assert_eq!(beacon, Some(56_000_011));
+        assert_eq!(count_covered(&sensors, 10), 26);
+    }
+
+    #[test]
+    fn coverage_shrinks_away_from_the_sensor() {
+        // The sensor reaches 9 positions away, up to its beacon.
+        let (sensor, beacon) = ((8, 7), (2, 10));
+
+        assert_eq!(coverage_at(&sensor, &beacon, 7), Some(-1..=17));
+        assert_eq!(coverage_at(&sensor, &beacon, 10), Some(2..=14));
+        assert_eq!(coverage_at(&sensor, &beacon, 16), Some(8..=8));
+        assert_eq!(coverage_at(&sensor, &beacon, -2), Some(8..=8));
+        assert_eq!(coverage_at(&sensor, &beacon, 17), None);
+        assert_eq!(coverage_at(&sensor, &beacon, -3), None);
+    }
+
+    #[test]
+    fn merged_coverage_joins_adjacent_and_nested_ranges() {
+        // Two sensors covering 1..=3 and 4..=6 of the row they are on.
+        let adjacent = [((2, 0), (3, 0)), ((5, 0), (6, 0))];
+        assert_eq!(merged_coverage(&adjacent, 0), [1..=6]);
+        assert_eq!(count_covered(&adjacent, 0), 2);
+
+        // A sensor covering 5..=15 of its row, and another one covering only 10..=10.
+        let nested = [((10, 0), (10, 5)), ((10, 1), (11, 1))];
+        assert_eq!(merged_coverage(&nested, 0), [5..=15]);
+        assert_eq!(count_covered(&nested, 0), 10);
+    }
+
+    #[test]
+    fn render_shows_the_single_uncovered_position() {
+        let sensors = parse_sensors(EXAMPLE).unwrap();
+
+        let region = render_region(&sensors, 20).unwrap();
+        let rows = region.lines().collect::<Vec<_>>();
+
+        assert_eq!(rows.len(), 21);
+        assert_eq!(region.matches('.').count(), 1);
+        assert_eq!(rows[11].find('.'), Some(14));
+        assert_eq!(rows[18].chars().nth(2), Some('S'));
+        assert_eq!(rows[16].chars().nth(10), Some('B'));
+
+        assert!(render_region(&sensors, MAX_RENDER_BOUND + 1).is_err());
+    }
+
+    #[test]
+    fn parses_the_example_sensors() {
+        let sensors = parse_sensors(EXAMPLE).unwrap();
+
+        assert_eq!(sensors.len(), 14);
+        assert_eq!(sensors[0], ((2, 18), (-2, 15)));
+        assert_eq!(sensors[13], ((20, 1), (15, 3)));
+
+        assert_eq!(
+            parse_sensors("Sensor at x=2, y=18: closest beacon is at x=-2\n"),
+            Err(ParseError::new(
+                1,
+                "Missing y coordinate in \" closest beacon is at x=-2\""
+            ))
+        );
+    }
+
+    #[test]
+    fn rows_without_coverage_or_with_a_gap() {
+        let sensors = parse_sensors(EXAMPLE).unwrap();
+        assert_eq!(merged_coverage(&sensors, -100), []);
+        assert_eq!(count_covered(&sensors, -100), 0);
+
+        // Two sensors covering both ends of their row, with a gap in between.
+        let sensors = [((0, 0), (1, 0)), ((10, 0), (11, 0))];
+        assert_eq!(merged_coverage(&sensors, 0), [-1..=1, 9..=11]);
+        assert_eq!(count_covered(&sensors, 0), 2);
+    }
+
+    #[test]
+    fn find_beacon_stops_at_the_gap() {
+        let sensors = parse_sensors(EXAMPLE).unwrap();
+
+        let (beacon, rows_scanned) = find_beacon(&sensors, 20).unwrap();
+
+        assert_eq!(beacon, (14, 11));
+        assert_eq!(rows_scanned, 12);
+        assert!(rows_scanned <= 21);
+
+        // The beacon is outside of a smaller range, so there is no gap in it.
+        assert_eq!(find_beacon(&sensors, 10), None);
+    }
 }
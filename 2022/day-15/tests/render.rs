@@ -0,0 +1,35 @@
+use std::process::{Command, Output};
+
+/// Run the solution against the sample with `--render` and the given flags.
+fn render(flags: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_day-15"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .args(["--example", "--render"])
+        .args(flags)
+        .output()
+        .expect("Couldn't run day-15")
+}
+
+#[test]
+fn renders_up_to_the_default_bound() {
+    let output = render(&[]);
+
+    assert!(output.status.success());
+    // The 21 rows of the region, an empty line and the two answers.
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap().lines().count(),
+        24
+    );
+}
+
+#[test]
+fn rejects_an_invalid_bound() {
+    let output = render(&["x"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Invalid value \"x\" for --render\n"
+    );
+}
@@ -1,5 +1,9 @@
-use once_cell::sync::OnceCell;
-use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use aoc_common::{
+    answers_only, extra_output, input_filename, print_answers, profile, read_input, report_error,
+    verbose, Memo, ParseError,
+};
 
 /// A struct which holds the data of a valve location.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -8,173 +12,789 @@ struct Valve {
     tunnels: BTreeSet<String>,
 }
 
-/// Read the input scan into a graph of valves.
-fn read_scan(filename: &str) -> BTreeMap<String, Valve> {
-    std::fs::read_to_string(filename)
-        .unwrap()
+/// Parse a valve and its name from a line of the input scan.
+fn parse_valve(line: &str) -> Result<(String, Valve), String> {
+    let name = line
+        .strip_prefix("Valve ")
+        .and_then(|rest| rest.get(..2))
+        .ok_or("Missing the valve name")?
+        .to_string();
+    let equal_index = line.find("=").ok_or("Missing the flow rate")? + 1;
+    let semi_index = line.find(";").ok_or("Missing \";\" after the flow rate")?;
+    let flow_rate = line
+        .get(equal_index..semi_index)
+        .and_then(|flow_rate| flow_rate.parse().ok())
+        .ok_or("Invalid flow rate")?;
+    let tunnels_index = line.find("valve").ok_or("Missing the tunnels")?;
+    let tunnels_string = line.get(tunnels_index + 5..).unwrap();
+    let tunnels_string = if tunnels_string.starts_with("s") {
+        tunnels_string.get(2..)
+    } else {
+        tunnels_string.get(1..)
+    }
+    .ok_or("Missing the tunnels")?;
+
+    let tunnels = tunnels_string
+        .split(", ")
+        .map(|string| string.to_string())
+        .collect();
+
+    Ok((name, Valve { flow_rate, tunnels }))
+}
+
+/// Parse the input scan into a graph of valves. Returns an error with the line number of the
+/// first valve that couldn't be parsed.
+fn parse_scan(input: &str) -> Result<BTreeMap<String, Valve>, ParseError> {
+    input
         .lines()
-        .map(|line| {
-            let name = line.get(6..8).unwrap().to_string();
-            let equal_index = line.find("=").unwrap() + 1;
-            let semi_index = line.find(";").unwrap();
-            let flow_rate = line.get(equal_index..semi_index).unwrap().parse().unwrap();
-            let tunnels_index = line.find("valve").unwrap();
-            let tunnels_string = line.get(tunnels_index + 5..).unwrap();
-            let tunnels_string = if tunnels_string.starts_with("s") {
-                tunnels_string.get(2..).unwrap()
-            } else {
-                tunnels_string.get(1..).unwrap()
-            };
+        .enumerate()
+        .map(|(index, line)| parse_valve(line).map_err(|error| ParseError::new(index + 1, error)))
+        .collect()
+}
 
-            let tunnels = tunnels_string
-                .split(", ")
-                .map(|string| string.to_string())
-                .collect();
+/// Describe the graph of valves with each valve, its flow rate and the valves its tunnels lead
+/// to, one valve per line. The valves and tunnels are sorted alphabetically.
+fn describe(valves: &BTreeMap<String, Valve>) -> String {
+    valves
+        .iter()
+        .map(|(name, valve)| {
+            let tunnels = valve.tunnels.iter().cloned().collect::<Vec<_>>().join(", ");
 
-            (name, Valve { flow_rate, tunnels })
+            format!(
+                "Valve {name} has flow rate={}; tunnels lead to valves {tunnels}",
+                valve.flow_rate
+            )
         })
-        .collect()
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-/// Map the graph of valves into vectors of flow rates and tunnels.
-fn map_tunnels_to_ints(tunnels: BTreeMap<String, Valve>) -> (Vec<u32>, Vec<Vec<u32>>) {
-    let mut name_map = BTreeMap::new();
+/// A struct which maps the names of valves to their indexes and back.
+#[derive(Debug, Clone, Default)]
+struct ValveNames {
+    names: Vec<String>,
+    indexes: BTreeMap<String, u32>,
+}
 
-    // Map the names of valves to the indexes of the valves.
-    tunnels.iter().enumerate().for_each(|(index, (name, _))| {
-        if !name_map.contains_key(name) {
-            name_map.insert(name.clone(), index as u32);
+impl ValveNames {
+    /// Add a valve name and give it the next index, unless the name already has an index.
+    fn insert(&mut self, name: &str) {
+        if !self.indexes.contains_key(name) {
+            self.indexes
+                .insert(name.to_string(), self.names.len() as u32);
+            self.names.push(name.to_string());
         }
-    });
+    }
+
+    /// Get the name of the valve at the given index.
+    fn index_to_name(&self, index: u32) -> &str {
+        &self.names[index as usize]
+    }
+
+    /// Get the index of the valve with the given name.
+    fn name_to_index(&self, name: &str) -> u32 {
+        *self.indexes.get(name).unwrap()
+    }
+}
+
+/// Map the graph of valves into vectors of flow rates and tunnels, along with the mapping between
+/// the names and indexes of the valves.
+fn map_tunnels_to_ints(tunnels: &BTreeMap<String, Valve>) -> (Vec<u32>, Vec<Vec<u32>>, ValveNames) {
+    let mut name_map = ValveNames::default();
+
+    // Map the names of valves to the indexes of the valves.
+    tunnels.keys().for_each(|name| name_map.insert(name));
 
     // Create a vector of flow rates. The index of the flow rate is the index of the valve.
-    let flow_map = tunnels.iter().map(|(_, valve)| valve.flow_rate).collect();
+    let flow_map = tunnels.values().map(|valve| valve.flow_rate).collect();
 
     // Createt a vector of vectors of tunnels. The index of the vector of tunnels is the index of
     // the valve which can lead to the valves in the vector. We need to map each tunnel to the
     // index of that valve.
     let tunnel_map = tunnels
-        .iter()
-        .map(|(_, valves)| {
+        .values()
+        .map(|valves| {
             valves
                 .tunnels
                 .iter()
-                .map(|tunnel| *name_map.get(tunnel).unwrap())
+                .map(|tunnel| name_map.name_to_index(tunnel))
                 .collect()
         })
         .collect();
 
-    (flow_map, tunnel_map)
+    (flow_map, tunnel_map, name_map)
+}
+
+/// Find which valves can be reached from the start valve (index 0) with a BFS through the
+/// tunnels.
+fn reachable_from_start(tunnels: &[Vec<u32>]) -> Vec<bool> {
+    let mut reachable = vec![false; tunnels.len()];
+    let mut queue = VecDeque::from([0]);
+
+    reachable[0] = true;
+
+    while let Some(valve) = queue.pop_front() {
+        for &tunnel in &tunnels[valve] {
+            if !reachable[tunnel as usize] {
+                reachable[tunnel as usize] = true;
+                queue.push_back(tunnel as usize);
+            }
+        }
+    }
+
+    reachable
+}
+
+/// Get a warning for every valve with flow which can't be reached from the start valve, as the
+/// input is likely malformed if there are any.
+fn unreachable_warnings(flow: &[u32], tunnels: &[Vec<u32>], names: &ValveNames) -> Vec<String> {
+    let reachable = reachable_from_start(tunnels);
+
+    flow.iter()
+        .enumerate()
+        .filter(|(index, flow_rate)| **flow_rate > 0 && !reachable[*index])
+        .map(|(index, flow_rate)| {
+            format!(
+                "Warning: valve {} has flow rate={flow_rate} but can't be reached from {}",
+                names.index_to_name(index as u32),
+                names.index_to_name(0)
+            )
+        })
+        .collect()
+}
+
+/// Get the indexes of the valves with a positive flow rate - the only valves worth opening.
+fn useful_valves(flows: &[u32]) -> Vec<u32> {
+    flows
+        .iter()
+        .enumerate()
+        .filter(|(_, flow)| **flow > 0)
+        .map(|(index, _)| index as u32)
+        .collect()
+}
+
+/// Get the bit of each valve in the opened valves set. Only the useful valves get a bit, so the
+/// set stays small even if there are many valves with no flow.
+fn valve_bits(flows: &[u32]) -> Vec<Option<u32>> {
+    let mut bits = vec![None; flows.len()];
+
+    for (bit, valve) in useful_valves(flows).into_iter().enumerate() {
+        bits[valve as usize] = Some(bit as u32);
+    }
+
+    bits
 }
 
-/// A hash map cache for our recursive calls. We want to reduce computation so we skip each
-/// invocation of a already seen set of inputs and return the outcome of that invocation.
-static mut CACHE: OnceCell<HashMap<String, u32>> = OnceCell::new();
+/// A set of opened valves as a bit set, indexed by the bit of each valve from `valve_bits`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+struct OpenSet(u64);
+
+impl OpenSet {
+    /// Check if the valve with the given bit is open.
+    pub fn is_open(&self, bit: u32) -> bool {
+        self.0 & (1 << bit) != 0
+    }
 
-/// We recursively compute the maximum flow rate starting from the valve `valve` given the opened
-/// valves `opened_valves`, minutes available `minutes_available` and number of other players
-/// `other_players`.
-fn max_flow_rate(
+    /// Get the set with the valve with the given bit opened as well. Opening an already opened
+    /// valve leaves the set as it is.
+    pub fn open(&self, bit: u32) -> Self {
+        Self(self.0 | (1 << bit))
+    }
+
+    /// Count the opened valves.
+    pub fn count(&self) -> u32 {
+        self.0.count_ones()
+    }
+}
+
+/// The flow stored in the memo for a search state. States whose search was cut short only know an
+/// upper bound of their flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flow {
+    Exact(u32),
+    AtMost(u32),
+}
+
+/// A state of the search - the valve the current player is at, the opened valves, the minutes the
+/// current player has left and the number of players who go after them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct State {
     valve: u32,
-    valves: &Vec<u32>,
-    tunnels: &Vec<Vec<u32>>,
-    opened_valves: u64,
+    opened_valves: OpenSet,
     minutes_available: u32,
     other_players: u32,
-) -> u32 {
-    // If there are no minutes left we check if there are more players to compute for.
-    if minutes_available == 0 {
-        // If there are more players to compute for, we start at the start valve and reset the
-        // minutes available to 26, but we keep the same valves open.
-        return if other_players > 0 {
-            max_flow_rate(0, valves, tunnels, opened_valves, 26, other_players - 1)
-        // Otherwise we just return 0.
-        } else {
-            0
-        };
+}
+
+impl State {
+    /// Create the state of the first player at the start valve, with no valves opened yet.
+    fn start(minutes_available: u32, other_players: u32) -> Self {
+        Self {
+            valve: 0,
+            opened_valves: OpenSet::default(),
+            minutes_available,
+            other_players,
+        }
     }
 
-    // We create a key to check for cached invocations.
-    let key = format!("{valve}-{opened_valves}-{minutes_available}-{other_players}");
+    /// Get the state after opening the valve with the given bit, which takes a minute.
+    fn open(&self, bit: u32) -> Self {
+        Self {
+            opened_valves: self.opened_valves.open(bit),
+            minutes_available: self.minutes_available - 1,
+            ..*self
+        }
+    }
 
-    // If there exists a invocation under the computed key, we return that value.
-    unsafe {
-        if let Some(value) = CACHE.get().unwrap().get(&key) {
-            return *value;
+    /// Get the state after moving through the tunnel to the given valve, which takes a minute.
+    fn move_to(&self, valve: u32) -> Self {
+        Self {
+            valve,
+            minutes_available: self.minutes_available - 1,
+            ..*self
         }
     }
 
-    // Our assumed max flow rate is initially 0.
-    let mut max_flow = 0;
+    /// Get the state of the next player, who starts at the start valve with 26 minutes but keeps
+    /// the same valves open.
+    fn next_player(&self) -> Self {
+        Self {
+            valve: 0,
+            minutes_available: 26,
+            other_players: self.other_players - 1,
+            ..*self
+        }
+    }
+}
 
-    // We create a bit mask for opening the current valve.
-    let mask = 1 << valve;
-    // We get the flow rate of the current valve.
-    let flow = valves.get(valve as usize).unwrap();
+/// The valves graph as the flow rates, tunnels and opened valves set bits of each valve, indexed
+/// by the valve index, along with the number of useful valves.
+struct Network<'a> {
+    valves: &'a [u32],
+    tunnels: &'a [Vec<u32>],
+    bits: &'a [Option<u32>],
+    useful: u32,
+}
 
-    // If the valve is not already opened, and the flow rate is more than 0 we call recursively
-    // with the valve open and minutes available decreased.
-    if opened_valves & mask == 0 && flow > &0 {
-        // Add the flow rate increase.
-        let flow_rate = flow * (minutes_available - 1);
+impl Network<'_> {
+    /// Get an upper bound for the flow we could still gain from the unopened valves. Every player
+    /// needs a minute to open a valve and at least another one to move to the next valve, so the
+    /// current player opens valves with at most `minutes_available - 1`, `minutes_available - 3`,
+    /// ... minutes left, and every other player with at most 25, 23, ... minutes left. The bound
+    /// gives the largest flow rates the most minutes.
+    fn remaining_useful_flow(
+        &self,
+        opened_valves: OpenSet,
+        minutes_available: u32,
+        other_players: u32,
+    ) -> u32 {
+        let mut unopened_flows = self
+            .bits
+            .iter()
+            .zip(self.valves)
+            .filter_map(|(bit, flow)| {
+                bit.filter(|bit| !opened_valves.is_open(*bit))
+                    .map(|_| *flow)
+            })
+            .collect::<Vec<_>>();
+        unopened_flows.sort_unstable_by(|a, b| b.cmp(a));
 
-        // Create the new opened valves value.
-        let new_opened = opened_valves | mask;
+        let mut minutes_left = (1..minutes_available)
+            .rev()
+            .step_by(2)
+            .chain((0..other_players).flat_map(|_| (1..26).rev().step_by(2)))
+            .collect::<Vec<_>>();
+        minutes_left.sort_unstable_by(|a, b| b.cmp(a));
 
-        // We find the max between the current max flow rate and the flow rate of the next
-        // recursive call with this valve open.
-        max_flow = max_flow.max(
-            flow_rate
-                + max_flow_rate(
-                    valve,
-                    valves,
-                    tunnels,
-                    new_opened,
-                    minutes_available - 1,
-                    other_players,
-                ),
-        );
+        unopened_flows
+            .iter()
+            .zip(minutes_left)
+            .map(|(flow, minutes)| flow * minutes)
+            .sum()
     }
 
-    // For all the tunnels this valve location is connected to, we recurse and find the max flow
-    // rate.
-    for &tunnel in tunnels.get(valve as usize).unwrap() {
-        max_flow = max_flow.max(max_flow_rate(
-            tunnel,
-            valves,
-            tunnels,
-            opened_valves,
-            minutes_available - 1,
-            other_players,
-        ));
+    /// Find the maximum flow rate we could still gain from the given search state.
+    fn max_flow_rate_from(&self, memo: &mut Memo<State, Flow>, state: State) -> u32 {
+        // The best flow only ever holds flows reachable from the state we start at, so the search
+        // as a whole is never cut short.
+        let mut best = 0;
+
+        let Flow::Exact(max_flow) = self.max_flow_rate(memo, state, 0, &mut best) else {
+            unreachable!();
+        };
+
+        max_flow
     }
 
-    // We update the cache for this call with the max flow we calculated.
-    unsafe {
-        CACHE.get_mut().unwrap().insert(key, max_flow);
+    /// We recursively compute the maximum flow rate we could still gain from the search state
+    /// `state`. The flow gained before reaching the state is `current`, and the best total flow
+    /// found so far is kept up to date in `best`. Already computed states are looked up in `memo`.
+    ///
+    /// Moves whose optimistic bound added to `current` can't reach `best` are skipped. If a move
+    /// we skipped might have beaten the flow we found, we only know an upper bound of the flow of
+    /// the state, which is what we return and store in the memo. Such a bound doesn't depend on
+    /// `current`, so it can cut the state short again whenever it is reached with a low enough
+    /// flow.
+    fn max_flow_rate(
+        &self,
+        memo: &mut Memo<State, Flow>,
+        state: State,
+        current: u32,
+        best: &mut u32,
+    ) -> Flow {
+        profile::record_call();
+
+        // If all the useful valves are open there is no more flow to gain.
+        if state.opened_valves.count() == self.useful {
+            *best = (*best).max(current);
+            return Flow::Exact(0);
+        }
+
+        // If there are no minutes left we check if there are more players to compute for.
+        if state.minutes_available == 0 {
+            // If there are more players to compute for, the next player starts at the start
+            // valve, but we keep the same valves open.
+            if state.other_players > 0 {
+                return self.max_flow_rate(memo, state.next_player(), current, best);
+            }
+
+            // Otherwise there is no more flow to gain.
+            *best = (*best).max(current);
+            return Flow::Exact(0);
+        }
+
+        // If the state is already computed, we return its flow. If it was cut short and still
+        // can't reach the best flow, it is cut short again.
+        match memo.get(&state) {
+            Some(Flow::Exact(max_flow)) => {
+                *best = (*best).max(current + max_flow);
+                return Flow::Exact(max_flow);
+            }
+            Some(Flow::AtMost(bound)) if current + bound < *best => return Flow::AtMost(bound),
+            _ => {}
+        }
+
+        // Our assumed max flow rate is initially 0, as is the most the moves we cut short could
+        // gain.
+        let mut max_flow = 0;
+        let mut cut_short = 0;
+
+        // If the valve is useful - it has a bit in the opened valves set - and is not already
+        // opened, we call recursively with the valve open and minutes available decreased.
+        let valve = state.valve as usize;
+        if let Some(bit) = self.bits[valve].filter(|bit| !state.opened_valves.is_open(*bit)) {
+            // Add the flow rate increase.
+            let flow_rate = self.valves[valve] * (state.minutes_available - 1);
+
+            // We find the max between the current max flow rate and the flow rate of the next
+            // recursive call with this valve open.
+            match self.max_flow_rate(memo, state.open(bit), current + flow_rate, best) {
+                Flow::Exact(flow) => max_flow = max_flow.max(flow_rate + flow),
+                Flow::AtMost(bound) => cut_short = cut_short.max(flow_rate + bound),
+            }
+        }
+
+        // Moving through a tunnel can gain at most the remaining useful flow after the move.
+        let bound = self.remaining_useful_flow(
+            state.opened_valves,
+            state.minutes_available - 1,
+            state.other_players,
+        );
+
+        // For all the tunnels this valve location is connected to, we recurse and find the max
+        // flow rate.
+        for &tunnel in &self.tunnels[valve] {
+            // If the bound can't reach the best flow found so far, no tunnel can.
+            if current + bound < *best {
+                cut_short = cut_short.max(bound);
+                break;
+            }
+
+            match self.max_flow_rate(memo, state.move_to(tunnel), current, best) {
+                Flow::Exact(flow) => max_flow = max_flow.max(flow),
+                Flow::AtMost(bound) => cut_short = cut_short.max(bound),
+            }
+        }
+
+        // If none of the moves we cut short could beat the flow we found, it is the exact maximum.
+        let flow = if cut_short <= max_flow {
+            Flow::Exact(max_flow)
+        } else {
+            Flow::AtMost(cut_short)
+        };
+
+        *best = (*best).max(current + max_flow);
+        memo.insert(state, flow);
+
+        flow
     }
 
-    max_flow
+    /// Find the best plan for opening valves by starting at the start valve with the given
+    /// minutes available and number of players. Returns the max flow rate along with the valves
+    /// opened and the minute each was opened in. The plan is reconstructed from the memo by
+    /// following the moves which keep the max flow rate the same.
+    fn best_plan(
+        &self,
+        memo: &mut Memo<State, Flow>,
+        minutes: u32,
+        players: u32,
+    ) -> (u32, Vec<(u32, u32)>) {
+        let mut state = State::start(minutes, players - 1);
+        let max_flow = self.max_flow_rate_from(memo, state);
+        let mut plan = vec![];
+        let mut start_minutes = minutes;
+
+        loop {
+            // When the current player runs out of time, the next player starts at the start valve
+            // with 26 minutes, just like in the search.
+            if state.minutes_available == 0 {
+                if state.other_players == 0 {
+                    break;
+                }
+
+                state = state.next_player();
+                start_minutes = state.minutes_available;
+            }
+
+            let flow = self.max_flow_rate_from(memo, state);
+
+            // If there is no more flow to gain, the rest of the plan doesn't matter.
+            if flow == 0 {
+                break;
+            }
+
+            let valve = state.valve as usize;
+            let bit = self.bits[valve].filter(|bit| !state.opened_valves.is_open(*bit));
+            let flow_rate = self.valves[valve] * (state.minutes_available - 1);
+
+            // Open the current valve if that is the move which leads to the max flow rate.
+            if let Some(bit) = bit.filter(|bit| {
+                flow_rate > 0 && flow_rate + self.max_flow_rate_from(memo, state.open(*bit)) == flow
+            }) {
+                plan.push((state.valve, start_minutes - state.minutes_available + 1));
+                state = state.open(bit);
+                continue;
+            }
+
+            // Otherwise move through the tunnel which leads to the max flow rate.
+            let tunnel = *self.tunnels[valve]
+                .iter()
+                .find(|&&tunnel| self.max_flow_rate_from(memo, state.move_to(tunnel)) == flow)
+                .unwrap();
+            state = state.move_to(tunnel);
+        }
+
+        (max_flow, plan)
+    }
 }
 
 fn main() {
     // Get the valves graph from the input scan.
-    let valves = read_scan("input.txt");
+    let input = match read_input(input_filename()) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    let valves = match parse_scan(&input) {
+        Ok(valves) => valves,
+        Err(error) => {
+            eprintln!("{}", report_error(&error, &input, verbose()));
+            std::process::exit(1);
+        }
+    };
+
+    // Only check that the input is valid if requested.
+    if std::env::args().any(|arg| arg == "--validate") {
+        println!("ok, {} valves", valves.len());
+        return;
+    }
+
     // We map the valves to vectors.
-    let (flow, tunnels) = map_tunnels_to_ints(valves);
+    let (flow, tunnels, names) = map_tunnels_to_ints(&valves);
+
+    // Print the valves graph and the valve indexes if requested.
+    if extra_output("--describe") {
+        println!("{}\n", describe(&valves));
+
+        for index in 0..flow.len() as u32 {
+            println!("{index}: {}", names.index_to_name(index));
+        }
+
+        println!("\n{} useful valves", useful_valves(&flow).len());
 
-    // We initialize the cache.
-    unsafe {
-        CACHE.set(HashMap::new()).unwrap();
+        println!();
     }
 
-    // Calculate the max flow rate for one player and 30 minutes available.
-    let max_flow = max_flow_rate(0, &flow, &tunnels, 0, 30, 0);
+    // Warn about valves with flow that can never be opened, as the input is likely malformed,
+    // unless only the answers should be printed.
+    if !answers_only() {
+        for warning in unreachable_warnings(&flow, &tunnels, &names) {
+            eprintln!("{warning}");
+        }
+    }
 
-    println!("{max_flow}");
+    // We create the network and the memo for the search.
+    let bits = valve_bits(&flow);
+    let network = Network {
+        valves: &flow,
+        tunnels: &tunnels,
+        bits: &bits,
+        useful: useful_valves(&flow).len() as u32,
+    };
+    let mut memo = Memo::new();
+
+    // Calculate the max flow rate for one player and 30 minutes available.
+    let max_flow = network.max_flow_rate_from(&mut memo, State::start(30, 0));
 
     // Calculate the max flow rate for two players and 26 minutes available.
-    let max_flow_two_people = max_flow_rate(0, &flow, &tunnels, 0, 26, 1);
+    let max_flow_two_people = network.max_flow_rate_from(&mut memo, State::start(26, 1));
+
+    // Print the valves opened by the best plans if requested.
+    if extra_output("--plan") {
+        for (minutes, players) in [(30, 1), (26, 2)] {
+            let (_, plan) = network.best_plan(&mut memo, minutes, players);
+
+            println!("== {players} player(s), {minutes} minutes ==");
+
+            for (valve, minute) in plan {
+                println!("Minute {minute}: open valve {}", names.index_to_name(valve));
+            }
+
+            println!();
+        }
+    }
+
+    // Print the search counters when profiling.
+    #[cfg(feature = "profile")]
+    profile::print_summary();
+
+    print_answers(16, max_flow, max_flow_two_people);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = include_str!("../sample.txt");
+
+    /// Map the example scan into the flow rates, tunnels and opened valves set bits of the
+    /// network.
+    fn example_network() -> (Vec<u32>, Vec<Vec<u32>>, Vec<Option<u32>>) {
+        let (flow, tunnels, _) = map_tunnels_to_ints(&parse_scan(EXAMPLE).unwrap());
+        let bits = valve_bits(&flow);
+
+        (flow, tunnels, bits)
+    }
+
+    /// The search without any pruning - every reachable state is computed exactly once.
+    fn unpruned_max_flow_rate(network: &Network, memo: &mut Memo<State, u32>, state: State) -> u32 {
+        profile::record_call();
+
+        if state.opened_valves.count() == network.useful {
+            return 0;
+        }
+
+        if state.minutes_available == 0 {
+            return if state.other_players > 0 {
+                unpruned_max_flow_rate(network, memo, state.next_player())
+            } else {
+                0
+            };
+        }
+
+        memo.get_or_compute(state, |memo| {
+            let valve = state.valve as usize;
+            let mut max_flow = 0;
+
+            if let Some(bit) = network.bits[valve].filter(|bit| !state.opened_valves.is_open(*bit))
+            {
+                let flow_rate = network.valves[valve] * (state.minutes_available - 1);
+
+                max_flow = flow_rate + unpruned_max_flow_rate(network, memo, state.open(bit));
+            }
+
+            for &tunnel in &network.tunnels[valve] {
+                max_flow =
+                    max_flow.max(unpruned_max_flow_rate(network, memo, state.move_to(tunnel)));
+            }
 
-    println!("{max_flow_two_people}");
+            max_flow
+        })
+    }
+
+    #[test]
+    fn describes_the_example_alphabetically() {
+        let valves = parse_scan(EXAMPLE).unwrap();
+        let description = describe(&valves);
+        let lines = description.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 10);
+        assert_eq!(
+            lines[0],
+            "Valve AA has flow rate=0; tunnels lead to valves BB, DD, II"
+        );
+        assert_eq!(
+            lines[7],
+            "Valve HH has flow rate=22; tunnels lead to valves GG"
+        );
+
+        let names = lines.iter().map(|line| &line[6..8]).collect::<Vec<_>>();
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+
+        let (_, _, name_map) = map_tunnels_to_ints(&valves);
+        assert_eq!(name_map.index_to_name(0), "AA");
+        assert_eq!(name_map.index_to_name(9), "JJ");
+        assert_eq!(name_map.name_to_index("DD"), 3);
+    }
+
+    #[test]
+    fn warns_about_unreachable_valves_with_flow() {
+        let (flow, tunnels, names) = map_tunnels_to_ints(&parse_scan(EXAMPLE).unwrap());
+        assert!(reachable_from_start(&tunnels)
+            .iter()
+            .all(|reachable| *reachable));
+        assert!(unreachable_warnings(&flow, &tunnels, &names).is_empty());
+
+        // Cut the tunnels between HH and the rest, and add a valve without flow which can't be
+        // reached either.
+        let disconnected = EXAMPLE
+            .replace("tunnels lead to valves FF, HH", "tunnel leads to valve FF")
+            .replace("tunnel leads to valve GG", "tunnel leads to valve KK")
+            + "Valve KK has flow rate=0; tunnel leads to valve HH\n";
+        let (flow, tunnels, names) = map_tunnels_to_ints(&parse_scan(&disconnected).unwrap());
+
+        assert_eq!(
+            unreachable_warnings(&flow, &tunnels, &names),
+            ["Warning: valve HH has flow rate=22 but can't be reached from AA"]
+        );
+    }
+
+    #[test]
+    fn only_useful_valves_get_bits() {
+        let (flow, tunnels, bits) = example_network();
+
+        assert_eq!(useful_valves(&flow), [1, 2, 3, 4, 7, 9]);
+        assert_eq!(
+            bits,
+            [
+                None,
+                Some(0),
+                Some(1),
+                Some(2),
+                Some(3),
+                None,
+                None,
+                Some(4),
+                None,
+                Some(5)
+            ]
+        );
+
+        let network = Network {
+            valves: &flow,
+            tunnels: &tunnels,
+            bits: &bits,
+            useful: useful_valves(&flow).len() as u32,
+        };
+
+        assert_eq!(
+            network.max_flow_rate_from(&mut Memo::new(), State::start(30, 0)),
+            1651
+        );
+    }
+
+    #[test]
+    fn best_plan_adds_up_to_the_max_flow() {
+        let (flow, tunnels, bits) = example_network();
+        let network = Network {
+            valves: &flow,
+            tunnels: &tunnels,
+            bits: &bits,
+            useful: useful_valves(&flow).len() as u32,
+        };
+
+        for (minutes, players, expected) in [(30, 1, 1651), (26, 2, 1707)] {
+            let (max_flow, plan) = network.best_plan(&mut Memo::new(), minutes, players);
+
+            // Each valve releases its flow for the minutes left after the minute it's opened in.
+            let total = plan
+                .iter()
+                .map(|(valve, minute)| flow[*valve as usize] * (minutes - minute))
+                .sum::<u32>();
+
+            assert_eq!(max_flow, expected);
+            assert_eq!(total, expected);
+            assert!(plan.iter().all(|(valve, _)| flow[*valve as usize] > 0));
+        }
+    }
+
+    #[test]
+    fn opening_a_valve_is_idempotent() {
+        let opened = OpenSet::default().open(3);
+
+        assert!(opened.is_open(3));
+        assert!(!opened.is_open(2));
+        assert_eq!(opened.count(), 1);
+
+        assert_eq!(opened.open(3), opened);
+        assert_eq!(opened.open(3).count(), 1);
+
+        let opened = opened.open(0).open(63);
+        assert!(opened.is_open(0) && opened.is_open(63));
+        assert_eq!(opened.count(), 3);
+    }
+
+    #[test]
+    fn profile_counts_calls_and_cache_hits() {
+        let (flow, tunnels, bits) = example_network();
+        let network = Network {
+            valves: &flow,
+            tunnels: &tunnels,
+            bits: &bits,
+            useful: useful_valves(&flow).len() as u32,
+        };
+
+        profile::reset();
+        network.max_flow_rate_from(&mut Memo::new(), State::start(30, 0));
+        let counters = profile::counters();
+
+        assert!(counters.calls > 0);
+        assert!(counters.cache_hits > 0);
+        assert!(counters.cache_size > 0);
+    }
+
+    #[test]
+    fn pruning_visits_fewer_states() {
+        let (flow, tunnels, bits) = example_network();
+        let network = Network {
+            valves: &flow,
+            tunnels: &tunnels,
+            bits: &bits,
+            useful: useful_valves(&flow).len() as u32,
+        };
+
+        for (state, expected) in [(State::start(30, 0), 1651), (State::start(26, 1), 1707)] {
+            profile::reset();
+            assert_eq!(
+                network.max_flow_rate_from(&mut Memo::new(), state),
+                expected
+            );
+            let pruned = profile::counters().calls;
+
+            profile::reset();
+            assert_eq!(
+                unpruned_max_flow_rate(&network, &mut Memo::new(), state),
+                expected
+            );
+            let unpruned = profile::counters().calls;
+
+            assert!(
+                pruned < unpruned,
+                "{pruned} calls with pruning, {unpruned} without"
+            );
+        }
+    }
 }
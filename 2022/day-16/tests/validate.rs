@@ -0,0 +1,70 @@
+use std::process::{Command, Output};
+
+/// Run the solution with `--validate` from the given directory.
+fn validate(directory: &std::path::Path, flags: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_day-16"))
+        .current_dir(directory)
+        .arg("--validate")
+        .args(flags)
+        .output()
+        .expect("Couldn't run day-16")
+}
+
+#[test]
+fn validates_the_sample() {
+    let output = validate(env!("CARGO_MANIFEST_DIR").as_ref(), &["--example"]);
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "ok, 10 valves\n");
+}
+
+#[test]
+fn reports_the_first_bad_line() {
+    // Break a line of the sample and validate it as the input of a separate directory.
+    let sample = include_str!("../sample.txt");
+    let directory = std::env::temp_dir().join("day-16-bad-input");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("input.txt"),
+        sample.replacen("rate=13", "rate=x", 1),
+    )
+    .unwrap();
+
+    let output = validate(&directory, &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Line 2: Invalid flow rate\n"
+    );
+}
+
+#[test]
+fn verbose_report_shows_the_lines_around_the_error() {
+    let sample = include_str!("../sample.txt");
+    let directory = std::env::temp_dir().join("day-16-bad-input-verbose");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("input.txt"),
+        sample.replacen("rate=13", "rate=x", 1),
+    )
+    .unwrap();
+
+    let output = validate(&directory, &["--verbose"]);
+
+    assert_eq!(output.status.code(), Some(1));
+
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        [
+            "Line 2: Invalid flow rate",
+            "     1 | Valve AA has flow rate=0; tunnels lead to valves DD, II, BB",
+            ">    2 | Valve BB has flow rate=x; tunnels lead to valves CC, AA",
+            "     3 | Valve CC has flow rate=2; tunnels lead to valves DD, BB",
+            "     4 | Valve DD has flow rate=20; tunnels lead to valves CC, AA, EE",
+            "",
+        ]
+        .join("\n")
+    );
+}
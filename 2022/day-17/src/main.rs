@@ -1,4 +1,6 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+
+use aoc_common::{input_filename, print_answers, read_input};
 
 /// An enum that represents the direction of air coming from a jet.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -30,19 +32,20 @@ enum Rock {
 
 /// A enum that represents whether the rock moved or stopped/got jammed.
 enum MoveNext {
-    Stopped(HashSet<(u64, u64)>),
+    Stopped(u64, u64),
     Moved(u64, u64),
 }
 
 impl Rock {
-    /// Get the coordinates that the rock takes up.
-    fn get_coords(&self) -> HashSet<(u64, u64)> {
+    /// Get the rows that the rock takes up, from the bottom up, as bitmasks of the occupied
+    /// columns when the rock is at the left wall.
+    fn get_rows(&self) -> &'static [u8] {
         match self {
-            Rock::HorizontalLine => HashSet::from_iter([(0, 0), (1, 0), (2, 0), (3, 0)]),
-            Rock::Plus => HashSet::from_iter([(0, 1), (1, 1), (2, 1), (1, 2), (1, 0)]),
-            Rock::LShape => HashSet::from_iter([(0, 0), (1, 0), (2, 0), (2, 1), (2, 2)]),
-            Rock::VerticalLine => HashSet::from_iter([(0, 0), (0, 1), (0, 2), (0, 3)]),
-            Rock::Square => HashSet::from_iter([(0, 0), (1, 0), (0, 1), (1, 1)]),
+            Rock::HorizontalLine => &[0b1111],
+            Rock::Plus => &[0b010, 0b111, 0b010],
+            Rock::LShape => &[0b111, 0b100, 0b100],
+            Rock::VerticalLine => &[0b1, 0b1, 0b1, 0b1],
+            Rock::Square => &[0b11, 0b11],
         }
     }
 
@@ -58,12 +61,7 @@ impl Rock {
     }
 
     /// Move the rock with the given chamber, direction and current coordinates.
-    fn move_rock(
-        &self,
-        chamber: &HashSet<(u64, u64)>,
-        direction: &Direction,
-        coords: &(u64, u64),
-    ) -> MoveNext {
+    fn move_rock(&self, chamber: &Chamber, direction: &Direction, coords: &(u64, u64)) -> MoveNext {
         // If on the edge of the chamber stay in place, otherwise move in the given direction.
         let next_x = match direction {
             Direction::Left => {
@@ -82,34 +80,17 @@ impl Rock {
             }
         };
 
-        // If at the bottom of the chamber stay in place, otherwise move down.
-        let next_y = if coords.1 == 0 {
-            coords.1
-        } else {
-            coords.1 - 1
-        };
-
-        // Get the coordinates to check for collisions.
-        let coords_to_test = self.get_coords();
-
         // If there is a collision because of the move sideways, don't perform the move.
-        let new_x = if next_x != coords.0
-            && !coords_to_test
-                .iter()
-                .any(|(x, y)| chamber.contains(&(next_x + x, coords.1 + y)))
-        {
+        let new_x = if next_x != coords.0 && !chamber.collides(self, next_x, coords.1) {
             next_x
         } else {
             coords.0
         };
 
-        // If there is a collision because of the move down, don't move/get jammed/settled.
-        let new_y = if next_y != coords.1
-            && !coords_to_test
-                .iter()
-                .any(|(x, y)| chamber.contains(&(new_x + x, next_y + y)))
-        {
-            next_y
+        // If at the bottom of the chamber or there is a collision because of the move down,
+        // don't move/get jammed/settled.
+        let new_y = if coords.1 != 0 && !chamber.collides(self, new_x, coords.1 - 1) {
+            coords.1 - 1
         } else {
             coords.1
         };
@@ -119,19 +100,78 @@ impl Rock {
         if coords.1 > new_y {
             MoveNext::Moved(new_x, new_y)
         } else {
-            MoveNext::Stopped(
-                coords_to_test
-                    .iter()
-                    .map(|(x, y)| (new_x + x, new_y + y))
-                    .collect(),
-            )
+            MoveNext::Stopped(new_x, new_y)
         }
     }
 }
 
+/// A struct that represents the chamber as a stack of rows, where each row is a bitmask of the
+/// seven columns, with the lowest bit being the leftmost column.
+#[derive(Debug, Default)]
+struct Chamber {
+    rows: Vec<u8>,
+}
+
+impl Chamber {
+    /// Get the height of the rock formation in the chamber.
+    fn height(&self) -> u64 {
+        self.rows.len() as u64
+    }
+
+    /// Check if the rock would overlap with any settled rocks at the given coordinates.
+    fn collides(&self, rock: &Rock, x: u64, y: u64) -> bool {
+        rock.get_rows().iter().enumerate().any(|(dy, row)| {
+            self.rows
+                .get(y as usize + dy)
+                .is_some_and(|chamber_row| chamber_row & (row << x) != 0)
+        })
+    }
+
+    /// Settle the rock at the given coordinates.
+    fn place(&mut self, rock: &Rock, x: u64, y: u64) {
+        for (dy, row) in rock.get_rows().iter().enumerate() {
+            let index = y as usize + dy;
+
+            if index >= self.rows.len() {
+                self.rows.resize(index + 1, 0);
+            }
+
+            self.rows[index] |= row << x;
+        }
+    }
+
+    /// Check if all of the columns of the row are occupied.
+    fn is_row_full(&self, y: u64) -> bool {
+        self.rows.get(y as usize) == Some(&0b111_1111)
+    }
+
+    /// Get the height of each column, that is the row above the highest occupied cell of the
+    /// column, or 0 if the column is empty.
+    fn column_heights(&self) -> [u64; 7] {
+        let mut heights = [0; 7];
+
+        for (column, height) in heights.iter_mut().enumerate() {
+            *height = self
+                .rows
+                .iter()
+                .rposition(|row| row & (1 << column) != 0)
+                .map_or(0, |y| y as u64 + 1);
+        }
+
+        heights
+    }
+
+    /// Get the depth of each column relative to the top of the rock formation. This describes the
+    /// shape of the surface regardless of how high it is.
+    fn surface_profile(&self) -> [u64; 7] {
+        self.column_heights()
+            .map(|column_height| self.height() - column_height)
+    }
+}
+
 /// Read the jet directions from the input file.
 fn get_jets(filename: &str) -> Vec<Direction> {
-    std::fs::read_to_string(filename)
+    read_input(filename)
         .unwrap()
         .trim()
         .chars()
@@ -139,90 +179,376 @@ fn get_jets(filename: &str) -> Vec<Direction> {
         .collect()
 }
 
-/// Get the height of the rock formation after `number_of_rocks` rocks have settled.
-fn get_height(number_of_rocks: u64, directions: &[Direction]) -> u64 {
+/// All of the rock shapes in the order they fall.
+const ALL_ROCKS: [Rock; 5] = [
+    Rock::HorizontalLine,
+    Rock::Plus,
+    Rock::LShape,
+    Rock::VerticalLine,
+    Rock::Square,
+];
+
+/// Drop a rock into the chamber, pushing it with the jets and letting it fall until it settles.
+/// Returns the index of the last jet that pushed the rock.
+fn drop_rock<'a>(
+    chamber: &mut Chamber,
+    rock: &Rock,
+    jets: &mut impl Iterator<Item = (usize, &'a Direction)>,
+) -> usize {
+    // Mark the starting possition of the current rock.
+    let mut coords = (2, chamber.height() + 3);
+
+    loop {
+        // Get the next jet direction.
+        let (jet, direction) = jets.next().unwrap();
+
+        // Move the rock.
+        match rock.move_rock(chamber, direction, &coords) {
+            MoveNext::Moved(x, y) => coords = (x, y),
+            MoveNext::Stopped(x, y) => {
+                chamber.place(rock, x, y);
+                return jet;
+            }
+        }
+    }
+}
+
+/// Build the chamber after `number_of_rocks` rocks have settled.
+fn build_chamber(number_of_rocks: usize, directions: &[Direction]) -> Chamber {
+    let mut jets = directions.iter().enumerate().cycle();
+    let mut chamber = Chamber::default();
+
+    for rock in ALL_ROCKS.iter().cycle().take(number_of_rocks) {
+        drop_rock(&mut chamber, rock, &mut jets);
+    }
+
+    chamber
+}
+
+/// Export `rows` rows of the chamber, going down from the row below `top`, as an SVG image with a
+/// brown square for every settled rock cell. The chamber grows upwards while the y axis of an SVG
+/// grows downwards, so the rows are flipped - the row below `top` is drawn at the top of the image.
+fn to_svg(chamber: &Chamber, top: u64, rows: u64) -> String {
+    let rows = rows.min(top);
+
+    let rects = (top - rows..top)
+        .rev()
+        .flat_map(|y| {
+            let row = chamber.rows.get(y as usize).copied().unwrap_or(0);
+
+            (0..7).filter(move |x| row & (1 << x) != 0).map(move |x| {
+                format!(
+                    "<rect x=\"{x}\" y=\"{}\" width=\"1\" height=\"1\" fill=\"#8b5a2b\"/>",
+                    top - 1 - y
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 7 {rows}\" \
+         shape-rendering=\"crispEdges\">\n{}\n</svg>\n",
+        rects.join("\n")
+    )
+}
+
+/// A struct that represents a repeating pattern in the rock formation. After `start` rocks have
+/// settled, every `length` rocks increase the height of the formation by `height`.
+#[derive(Debug)]
+struct Cycle {
+    start: u64,
+    length: u64,
+    height: u64,
+}
+
+/// Simulate the rocks falling until `number_of_rocks` rocks have settled or the rock formation
+/// starts repeating. Returns the height of the formation after each number of settled rocks,
+/// starting with 0 rocks, along with the cycle if one was found.
+fn simulate(number_of_rocks: u64, directions: &[Direction]) -> (Vec<u64>, Option<Cycle>) {
     // Create a cyclical iterator of directions.
     let mut direction_iter = directions.iter().enumerate().cycle();
 
     // Create a cyclical iterator of rocks.
-    let all_rocks = vec![
-        Rock::HorizontalLine,
-        Rock::Plus,
-        Rock::LShape,
-        Rock::VerticalLine,
-        Rock::Square,
-    ];
-    let rocks = all_rocks.iter().cycle().take(number_of_rocks as usize);
+    let rocks = ALL_ROCKS.iter().cycle().take(number_of_rocks as usize);
 
     // Create the chamber.
-    let mut chamber = HashSet::<(u64, u64)>::new();
+    let mut chamber = Chamber::default();
 
-    // Keep track of the height of the rock formation.
-    let mut height: u64 = 0;
+    // Keep track of the height of the rock formation after each rock.
+    let mut heights = vec![0];
 
     // Keep track of fully closed chamber compartments.
     let mut floors = HashMap::new();
 
     // Iterate through all of the rocks.
     for (round, rock) in rocks.enumerate() {
-        // Mark the starting possition of the current rock.
-        let mut coords = (2, height + 3);
-
         // Capture the last jet index while moving the rock until it settles.
-        let current_jet = loop {
-            // Get the next jet direction.
-            let (jet, direction) = direction_iter.next().unwrap();
-
-            // Move the rock.
-            match rock.move_rock(&chamber, direction, &coords) {
-                MoveNext::Moved(x, y) => {
-                    coords = (x, y);
-                    continue;
-                }
-                MoveNext::Stopped(blocked) => {
-                    height = height.max(*blocked.iter().map(|(_, y)| y).max().unwrap() + 1);
-                    chamber.extend(blocked);
-                    break jet;
-                }
-            }
-        };
+        let current_jet = drop_rock(&mut chamber, rock, &mut direction_iter);
+
+        let height = chamber.height();
+        let settled = round as u64 + 1;
+
+        heights.push(height);
 
         // Check if there is a closed chamber compartement.
-        if (0..7).all(|x| chamber.contains(&(x, height - 2))) {
-            // If we already had a compartment at the current rock and jet index combination,
-            // Calculate the remainder of the height by using the cycles of the formation.
-            if let Some((first_iteration, first_height)) = floors.get(&(rock, current_jet)) {
-                let rocks_in_cycle = round - first_iteration;
-                let cycle_height = height - 1 - first_height;
-                let leftover_rounds = number_of_rocks - *first_iteration as u64;
-                let cycles_left = leftover_rounds / rocks_in_cycle as u64;
-                let leftover_rocks = leftover_rounds % rocks_in_cycle as u64;
-
-                return cycles_left * cycle_height
-                    // There will be rocks leftover from the cycle division as it might not be a
-                    // whole number. There will also be rocks from before we entered a cycle so we
-                    // also calculate the height for those.
-                    + get_height(*first_iteration as u64 + leftover_rocks, directions);
-            } else {
-                // Otherwise remember the current chambers location.
-                floors.insert((rock, current_jet), (round, height - 1));
+        if height >= 2 && chamber.is_row_full(height - 2) {
+            let key = (rock, current_jet, chamber.surface_profile());
+
+            // If we already had a compartment at the current rock, jet index and surface shape
+            // combination, the formation repeats from there on.
+            if let Some((first_settled, first_height)) = floors.get(&key) {
+                let cycle = Cycle {
+                    start: *first_settled,
+                    length: settled - first_settled,
+                    height: height - first_height,
+                };
+
+                return (heights, Some(cycle));
             }
+
+            // Otherwise remember the current chambers location.
+            floors.insert(key, (settled, height));
         }
     }
 
-    height
+    (heights, None)
+}
+
+/// Get the height of the rock formation after `number_of_rocks` rocks have settled. If the rock
+/// formation starts repeating, extrapolate the height using the cycle instead of simulating all
+/// of the rocks. Returns an error if the extrapolated height doesn't fit in a `u64`.
+fn get_height(number_of_rocks: u64, directions: &[Direction]) -> Result<u64, String> {
+    let (heights, cycle) = simulate(number_of_rocks, directions);
+
+    let Some(cycle) = cycle else {
+        return Ok(*heights.last().unwrap());
+    };
+
+    // Skip all of the whole cycles, and take the height of the rocks leftover after them from
+    // the first time through the cycle.
+    let cycles = (number_of_rocks - cycle.start) / cycle.length;
+    let leftover_rocks = (number_of_rocks - cycle.start) % cycle.length;
+
+    cycles
+        .checked_mul(cycle.height)
+        .and_then(|height| height.checked_add(heights[(cycle.start + leftover_rocks) as usize]))
+        .ok_or_else(|| {
+            format!(
+                "The height after {number_of_rocks} rocks overflows: {cycles} cycles of height {} \
+                 starting after {} rocks",
+                cycle.height, cycle.start
+            )
+        })
 }
 
 fn main() {
     // Get the jet directions from the input file.
-    let jets = get_jets("input.txt");
+    let jets = get_jets(input_filename());
+
+    // Export the chamber after 2022 rocks to `chamber.svg` if requested.
+    if std::env::args().any(|arg| arg == "--svg") {
+        let chamber = build_chamber(2022, &jets);
+        let svg = to_svg(&chamber, chamber.height(), chamber.height());
+
+        if let Err(error) = std::fs::write("chamber.svg", svg) {
+            eprintln!("Couldn't write chamber.svg: {error}");
+            std::process::exit(1);
+        }
+    }
 
-    // Calculate the height of the rock formation.
-    let height = get_height(2022, &jets);
+    // Calculate the height of the rock formation after 2022 rocks and after a trillion rocks.
+    match (
+        get_height(2022, &jets),
+        get_height(1_000_000_000_000, &jets),
+    ) {
+        (Ok(height), Ok(new_height)) => print_answers(17, height, new_height),
+        (Err(error), _) | (_, Err(error)) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    const EXAMPLE: &str = ">>><<><>><<<>><>>><<<>>><<<><<<>><>><<>>";
+
+    /// Parse the jet directions of the given jet pattern.
+    fn parse_jets(pattern: &str) -> Vec<Direction> {
+        pattern.chars().map(|char| Direction::new(&char)).collect()
+    }
 
-    // Calculate the height of the rock formation.
-    let new_height = get_height(1_000_000_000_000, &jets);
+    /// Get the height after `number_of_rocks` rocks have settled in a chamber stored as a set of
+    /// occupied `(x, y)` cells, the representation the bitmask chamber replaced.
+    fn set_chamber_height(number_of_rocks: usize, directions: &[Direction]) -> u64 {
+        let mut chamber = HashSet::<(u64, u64)>::new();
+        let mut height = 0;
+        let mut jets = directions.iter().cycle();
 
-    println!("{height}");
-    println!("{new_height}");
+        for rock in ALL_ROCKS.iter().cycle().take(number_of_rocks) {
+            // The cells of the rock when it is at the left wall, on the floor.
+            let cells = rock
+                .get_rows()
+                .iter()
+                .enumerate()
+                .flat_map(|(y, row)| {
+                    (0..7)
+                        .filter(move |x| row & (1 << x) != 0)
+                        .map(move |x| (x, y as u64))
+                })
+                .collect::<Vec<_>>();
+
+            let fits = |x: u64, y: u64| {
+                cells
+                    .iter()
+                    .all(|(dx, dy)| x + dx < 7 && !chamber.contains(&(x + dx, y + dy)))
+            };
+
+            let (mut x, mut y): (u64, u64) = (2, height + 3);
+
+            loop {
+                let next_x = match jets.next().unwrap() {
+                    Direction::Left => x.checked_sub(1),
+                    Direction::Right => Some(x + 1),
+                };
+
+                if let Some(next_x) = next_x.filter(|next_x| fits(*next_x, y)) {
+                    x = next_x;
+                }
+
+                if y == 0 || !fits(x, y - 1) {
+                    break;
+                }
+
+                y -= 1;
+            }
+
+            for (dx, dy) in cells {
+                chamber.insert((x + dx, y + dy));
+                height = height.max(y + dy + 1);
+            }
+        }
+
+        height
+    }
+
+    #[test]
+    fn bitmask_chamber_matches_the_set_chamber() {
+        let jets = parse_jets(EXAMPLE);
+
+        for number_of_rocks in [1, 2, 3, 5, 10, 50, 100, 500, 2022] {
+            assert_eq!(
+                build_chamber(number_of_rocks, &jets).height(),
+                set_chamber_height(number_of_rocks, &jets),
+                "after {number_of_rocks} rocks"
+            );
+        }
+
+        assert_eq!(build_chamber(2022, &jets).height(), 3068);
+    }
+
+    #[test]
+    fn get_height_matches_the_simulation() {
+        // The example never closes a row, so the puzzle input is needed to get a cycle to
+        // extrapolate from.
+        for jets in [
+            parse_jets(EXAMPLE),
+            parse_jets(include_str!("../input.txt").trim()),
+        ] {
+            // Build the chamber one rock at a time, the same as `build_chamber` does for each
+            // number of rocks.
+            let mut chamber = Chamber::default();
+            let mut jet_iter = jets.iter().enumerate().cycle();
+
+            for (round, rock) in ALL_ROCKS.iter().cycle().take(3000).enumerate() {
+                drop_rock(&mut chamber, rock, &mut jet_iter);
+
+                let number_of_rocks = round as u64 + 1;
+
+                assert_eq!(
+                    get_height(number_of_rocks, &jets),
+                    Ok(chamber.height()),
+                    "after {number_of_rocks} rocks"
+                );
+            }
+
+            assert_eq!(build_chamber(3000, &jets).height(), chamber.height());
+            assert_eq!(
+                get_height(2022, &jets),
+                Ok(build_chamber(2022, &jets).height())
+            );
+        }
+    }
+
+    #[test]
+    fn trillion_rock_height_is_exact_and_overflow_is_an_error() {
+        let jets = parse_jets(include_str!("../input.txt").trim());
+
+        // The cycle of the puzzle input starts after 437 rocks, and every 1690 rocks after that
+        // add 2647 to the height.
+        let (heights, cycle) = simulate(1_000_000_000_000, &jets);
+        let cycle = cycle.unwrap();
+        assert_eq!((cycle.start, cycle.length, cycle.height), (437, 1690, 2647));
+
+        let cycles = (1_000_000_000_000 - cycle.start) / cycle.length;
+        let leftover_rocks = (1_000_000_000_000 - cycle.start) % cycle.length;
+
+        assert_eq!(
+            get_height(1_000_000_000_000, &jets),
+            Ok(cycles * cycle.height + heights[(cycle.start + leftover_rocks) as usize])
+        );
+        assert_eq!(get_height(1_000_000_000_000, &jets), Ok(1_566_272_189_352));
+
+        // The most whole cycles whose height still fits in a `u64`, and one cycle past that.
+        let start_height = heights[cycle.start as usize];
+        let max_cycles = (u64::MAX - start_height) / cycle.height;
+        let max_rocks = cycle.start + max_cycles * cycle.length;
+
+        assert_eq!(
+            get_height(max_rocks, &jets),
+            Ok(max_cycles * cycle.height + start_height)
+        );
+        assert!(get_height(max_rocks + cycle.length, &jets).is_err());
+
+        // The height grows faster than the number of rocks, so the most rocks overflow it.
+        let error = get_height(u64::MAX, &jets).unwrap_err();
+        assert!(error.starts_with(&format!("The height after {} rocks overflows", u64::MAX)));
+    }
+
+    #[test]
+    fn surface_profile_of_a_known_chamber() {
+        // A horizontal rock in columns 1 to 4, with a vertical rock standing on column 3.
+        let chamber = Chamber {
+            rows: vec![0b0011110, 0b0001000, 0b0001000, 0b0001000],
+        };
+
+        assert_eq!(chamber.column_heights(), [0, 1, 1, 4, 1, 0, 0]);
+        assert_eq!(chamber.surface_profile(), [4, 3, 3, 0, 3, 4, 4]);
+
+        // An empty chamber is flat.
+        assert_eq!(Chamber::default().surface_profile(), [0; 7]);
+    }
+
+    #[test]
+    fn svg_has_a_rect_for_each_rock_cell() {
+        // A horizontal rock on the floor with the middle of a plus rock on top of it.
+        let chamber = Chamber {
+            rows: vec![0b0111100, 0b0001000],
+        };
+
+        let svg = to_svg(&chamber, chamber.height(), 10);
+
+        assert_eq!(svg.matches("<rect").count(), 5);
+        assert!(svg.contains("viewBox=\"0 0 7 2\""));
+        // The chamber grows upwards, so its top row is drawn at the top of the SVG.
+        assert!(svg.contains("<rect x=\"3\" y=\"0\""));
+        assert!(svg.contains("<rect x=\"2\" y=\"1\""));
+
+        // Only the top row is drawn when a single row is requested.
+        assert_eq!(to_svg(&chamber, 2, 1).matches("<rect").count(), 1);
+    }
 }
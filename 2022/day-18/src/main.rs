@@ -1,4 +1,10 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use aoc_common::{
+    answers_only, extra_output, flood_fill, input_filename, parse_lines, print_answers, read_input,
+    report_error, verbose, ParseError,
+};
 
 /// A struct that represents a 1x1x1 cube by its coordinates.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -8,18 +14,29 @@ struct Cube {
     z: i16,
 }
 
-impl Cube {
-    /// Create a new cube from a x,y,z string.
-    pub fn new(line: &str) -> Self {
-        let mut coords = line.split(",").map(|number| number.parse().unwrap());
-
-        Self {
-            x: coords.next().unwrap(),
-            y: coords.next().unwrap(),
-            z: coords.next().unwrap(),
-        }
+impl FromStr for Cube {
+    type Err = String;
+
+    /// Parse a cube from a x,y,z string.
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let coords = line
+            .split(",")
+            .map(|number| {
+                number
+                    .parse()
+                    .map_err(|_| format!("Invalid coordinate {number}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let [x, y, z] = coords[..] else {
+            return Err(format!("Expected 3 coordinates, got {}", coords.len()));
+        };
+
+        Ok(Self { x, y, z })
     }
+}
 
+impl Cube {
     /// Get a set of neighbours of the cube.
     fn get_neighbours(&self) -> HashSet<Self> {
         [
@@ -58,77 +75,234 @@ impl Cube {
     }
 }
 
-/// Read the cube coordinates from the input file into a set.
-fn read_cubes(filename: &str) -> HashSet<Cube> {
-    std::fs::read_to_string(filename)
-        .unwrap()
-        .lines()
-        .map(|line| Cube::new(line))
-        .collect()
+/// Parse the cube coordinates from the input into a set.
+fn parse_cubes(input: &str) -> Result<HashSet<Cube>, ParseError> {
+    let cubes = parse_lines::<Cube>(input)?;
+
+    Ok(cubes.into_iter().collect())
 }
 
-/// Visit all the cubes outside the given set to check how many sides are reachable from outside.
-/// Use BFS to visit all cubes.
-fn count_outside(cubes: &HashSet<Cube>) -> usize {
-    // Find the max coordinates in the set of cubes.
-    let (max_x, max_y, max_z) = cubes
-        .iter()
-        .fold((0, 0, 0), |(max_x, max_y, max_z), Cube { x, y, z }| {
-            (max_x.max(*x), max_y.max(*y), max_z.max(*z))
-        });
+/// Get the corners of the smallest box holding all of the given cubes - the cubes with the min and
+/// max coordinates. An empty set is bounded by the cube at the origin.
+fn bounds(cubes: &HashSet<Cube>) -> (Cube, Cube) {
+    let origin = Cube { x: 0, y: 0, z: 0 };
+    let first = cubes.iter().next().copied().unwrap_or(origin);
+
+    cubes.iter().fold((first, first), |(min, max), cube| {
+        (
+            Cube {
+                x: min.x.min(cube.x),
+                y: min.y.min(cube.y),
+                z: min.z.min(cube.z),
+            },
+            Cube {
+                x: max.x.max(cube.x),
+                y: max.y.max(cube.y),
+                z: max.z.max(cube.z),
+            },
+        )
+    })
+}
+
+/// Get the lower and upper bound cubes of the space around the given set, leaving a layer of
+/// empty cubes around it on every side.
+fn get_bounds(cubes: &HashSet<Cube>) -> (Cube, Cube) {
+    let (min, max) = bounds(cubes);
+
     // Create a start/lower bound cube.
     let start = Cube {
-        x: -1,
-        y: -1,
-        z: -1,
+        x: min.x - 1,
+        y: min.y - 1,
+        z: min.z - 1,
     };
     // Create a end/upper bound cube.
     let end = Cube {
-        x: max_x + 1,
-        y: max_y + 1,
-        z: max_z + 1,
+        x: max.x + 1,
+        y: max.y + 1,
+        z: max.z + 1,
     };
-    // Keep track of visited cubes.
-    let mut visited = HashSet::new();
-    // Create a queue of cubes to visit.
-    let mut queue = VecDeque::from_iter([start]);
-    // Keep running count of visible sides.
-    let mut count = 0;
-
-    while !queue.is_empty() {
-        let next = queue.pop_front().unwrap();
-
-        // If the cube is in the given set, increase the count.
-        if cubes.contains(&next) {
-            count += 1;
-            continue;
-        }
 
-        // If cube is not already visited and is in bounds, add it to the visited set.
-        // Also add its neighbours to the queue.
-        if !visited.contains(&next) && next.in_bounds(&start, &end) {
-            visited.insert(next);
+    (start, end)
+}
 
-            queue.extend(next.get_neighbours());
-        }
-    }
+/// Visit all the empty cubes reachable from outside the given set and return them.
+fn exterior_cells(cubes: &HashSet<Cube>) -> HashSet<Cube> {
+    let (start, end) = get_bounds(cubes);
 
-    count
+    // Only move to cubes which are in bounds and not in the given set.
+    flood_fill(start, |cube| {
+        cube.get_neighbours()
+            .into_iter()
+            .filter(|next| !cubes.contains(next) && next.in_bounds(&start, &end))
+    })
 }
 
-fn main() {
-    // Get the cubes from the input file.
-    let cubes = read_cubes("input.txt");
+/// The surface area of a set of cubes, split into the sides reachable from outside and the sides
+/// facing trapped pockets of air.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SurfaceSummary {
+    total: usize,
+    exterior: usize,
+    trapped: usize,
+}
 
+/// Measure the surface area of the given set of cubes. The exterior sides are the sides touching
+/// an exterior cell, so every other visible side faces a trapped cell.
+fn surface_summary(cubes: &HashSet<Cube>) -> SurfaceSummary {
     // Count all the visible sides.
-    let visible_sides = cubes
+    let total = cubes
         .iter()
-        .map(|cube| cube.visible_sides(&cubes))
+        .map(|cube| cube.visible_sides(cubes))
         .sum::<usize>();
 
     // Count the sides visible from the outside.
-    let count_outside = count_outside(&cubes);
+    let exterior = exterior_cells(cubes)
+        .iter()
+        .map(|cell| cubes.intersection(&cell.get_neighbours()).count())
+        .sum::<usize>();
+
+    SurfaceSummary {
+        total,
+        exterior,
+        trapped: total - exterior,
+    }
+}
+
+/// Find the empty cubes trapped inside the given set - the ones not reachable from outside.
+fn trapped_cells(cubes: &HashSet<Cube>) -> HashSet<Cube> {
+    let (start, end) = get_bounds(cubes);
+    let exterior = exterior_cells(cubes);
+
+    (start.x..=end.x)
+        .flat_map(|x| (start.y..=end.y).map(move |y| (x, y)))
+        .flat_map(|(x, y)| (start.z..=end.z).map(move |z| Cube { x, y, z }))
+        .filter(|cell| !cubes.contains(cell) && !exterior.contains(cell))
+        .collect()
+}
 
-    println!("{visible_sides}");
-    println!("{count_outside}");
+fn main() {
+    // Get the cubes from the input file.
+    let input = match read_input(input_filename()) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    let cubes = match parse_cubes(&input) {
+        Ok(cubes) => cubes,
+        Err(error) => {
+            eprintln!("{}", report_error(&error, &input, verbose()));
+            std::process::exit(1);
+        }
+    };
+
+    // Measure all the visible sides and the sides visible from the outside.
+    let summary = surface_summary(&cubes);
+
+    // Print the trapped empty cubes if requested.
+    if extra_output("--trapped") {
+        println!("Trapped cubes: {}\n", trapped_cells(&cubes).len());
+    }
+
+    // Print how the surface splits up, unless only the answers should be printed.
+    if !answers_only() {
+        println!(
+            "Total surface: {}, exterior: {}, trapped: {}\n",
+            summary.total, summary.exterior, summary.trapped
+        );
+    }
+
+    print_answers(18, summary.total, summary.exterior);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a set of cubes from their coordinates.
+    fn cubes(coords: &[(i16, i16, i16)]) -> HashSet<Cube> {
+        coords.iter().map(|&(x, y, z)| Cube { x, y, z }).collect()
+    }
+
+    #[test]
+    fn exterior_cells_leave_out_the_trapped_cell() {
+        // A shell of six cubes around the origin.
+        let shell = cubes(&[
+            (1, 0, 0),
+            (-1, 0, 0),
+            (0, 1, 0),
+            (0, -1, 0),
+            (0, 0, 1),
+            (0, 0, -1),
+        ]);
+        let origin = Cube { x: 0, y: 0, z: 0 };
+
+        let exterior = exterior_cells(&shell);
+        let (start, end) = get_bounds(&shell);
+        let not_reached = (start.x..=end.x)
+            .flat_map(|x| (start.y..=end.y).map(move |y| (x, y)))
+            .flat_map(|(x, y)| (start.z..=end.z).map(move |z| Cube { x, y, z }))
+            .filter(|cell| !shell.contains(cell) && !exterior.contains(cell))
+            .collect::<HashSet<_>>();
+
+        assert_eq!(not_reached, HashSet::from([origin]));
+        assert_eq!(trapped_cells(&shell), HashSet::from([origin]));
+    }
+
+    #[test]
+    fn example_surface_summary() {
+        let example = parse_cubes(include_str!("../sample.txt")).unwrap();
+
+        let summary = surface_summary(&example);
+
+        assert_eq!(
+            summary,
+            SurfaceSummary {
+                total: 64,
+                exterior: 58,
+                trapped: 6,
+            }
+        );
+        assert_eq!(summary.total - summary.exterior, summary.trapped);
+        // The single trapped cube faces the droplet on all six sides.
+        assert_eq!(trapped_cells(&example).len(), 1);
+    }
+
+    #[test]
+    fn bounds_span_negative_coordinates() {
+        // A rod from x=-3 to x=2, and a single cube away from it.
+        let mut droplet = (-3..=2).map(|x| (x, -1, -2)).collect::<Vec<_>>();
+        droplet.push((0, 4, 3));
+        let droplet = cubes(&droplet);
+
+        assert_eq!(
+            bounds(&droplet),
+            (
+                Cube {
+                    x: -3,
+                    y: -1,
+                    z: -2
+                },
+                Cube { x: 2, y: 4, z: 3 }
+            )
+        );
+        assert_eq!(
+            get_bounds(&droplet),
+            (
+                Cube {
+                    x: -4,
+                    y: -2,
+                    z: -3
+                },
+                Cube { x: 3, y: 5, z: 4 }
+            )
+        );
+
+        // Every side of the rod and the cube is reached from outside.
+        let summary = surface_summary(&droplet);
+        assert_eq!(summary.total, 6 * 4 + 2 + 6);
+        assert_eq!(summary.exterior, summary.total);
+        assert_eq!(summary.trapped, 0);
+    }
 }
@@ -0,0 +1,28 @@
+use std::process::Command;
+
+/// Run the solution against the sample with the given flags and return its output.
+fn run(flags: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_day-18"))
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .arg("--example")
+        .args(flags)
+        .output()
+        .expect("Couldn't run day-18");
+
+    assert!(output.status.success());
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn prints_the_whole_surface_summary() {
+    assert_eq!(
+        run(&[]),
+        "Total surface: 64, exterior: 58, trapped: 6\n\n64\n58\n"
+    );
+}
+
+#[test]
+fn leaves_out_the_summary_with_only_the_answers() {
+    assert_eq!(run(&["--answers-only"]), "64\n58\n");
+}
@@ -1,9 +1,16 @@
-use std::{collections::HashMap, hash::Hash};
+use std::{
+    fmt::Display,
+    ops::{Index, IndexMut},
+    str::FromStr,
+};
 
-use once_cell::sync::OnceCell;
+use aoc_common::{
+    answers_only, flag_value, input_filename, print_answers, profile, read_input, report_error,
+    verbose, Memo, ParseError,
+};
 
 /// An enum that represents a robot worker which can collect/crack a type of resource.
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Robot {
     Ore,
     Clay,
@@ -18,8 +25,25 @@ impl Robot {
 
         vec![Ore, Clay, Obsidian, Geode]
     }
+
+    /// Get the index of the robot/resource type when counts are stored in arrays.
+    pub fn index(&self) -> usize {
+        match self {
+            Robot::Ore => 0,
+            Robot::Clay => 1,
+            Robot::Obsidian => 2,
+            Robot::Geode => 3,
+        }
+    }
 }
 
+/// Counts of something for each robot/resource type, indexed by `Robot::index` - `Ore` is 0,
+/// `Clay` is 1, `Obsidian` is 2 and `Geode` is 3.
+type Counts = [i32; 4];
+
+/// The robots we start the puzzle with - a single ore robot.
+const STARTING_ROBOTS: Counts = [1, 0, 0, 0];
+
 /// A struct that keeps track of how many resources we have.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Storage {
@@ -42,13 +66,46 @@ impl Storage {
 
     /// Increase the count of resources gathered by the count of robots for each resource and the
     /// given number of iterations/minutes for gathering.
-    pub fn gather(&mut self, robots: &HashMap<Robot, i32>, iterations: i32) {
-        robots.iter().for_each(|(robot, count)| match robot {
-            Robot::Ore => self.ore += *count * iterations,
-            Robot::Clay => self.clay += *count * iterations,
-            Robot::Obsidian => self.obsidian += *count * iterations,
-            Robot::Geode => self.geode += *count * iterations,
-        });
+    pub fn gather(&mut self, robots: &Counts, iterations: i32) {
+        for robot in Robot::all_types() {
+            self[robot] += robots[robot.index()] * iterations;
+        }
+    }
+}
+
+impl Display for Storage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ore, {} clay, {} obsidian, {} geode",
+            self.ore, self.clay, self.obsidian, self.geode
+        )
+    }
+}
+
+impl Index<Robot> for Storage {
+    type Output = i32;
+
+    /// Get the amount of the resource the given robot type collects.
+    fn index(&self, robot: Robot) -> &Self::Output {
+        match robot {
+            Robot::Ore => &self.ore,
+            Robot::Clay => &self.clay,
+            Robot::Obsidian => &self.obsidian,
+            Robot::Geode => &self.geode,
+        }
+    }
+}
+
+impl IndexMut<Robot> for Storage {
+    /// Get a mutable reference to the amount of the resource the given robot type collects.
+    fn index_mut(&mut self, robot: Robot) -> &mut Self::Output {
+        match robot {
+            Robot::Ore => &mut self.ore,
+            Robot::Clay => &mut self.clay,
+            Robot::Obsidian => &mut self.obsidian,
+            Robot::Geode => &mut self.geode,
+        }
     }
 }
 
@@ -59,7 +116,7 @@ struct Blueprint {
     clay: i32,
     obsidian: (i32, i32),
     geode: (i32, i32),
-    max_spend: HashMap<Robot, i32>,
+    max_spend: Counts,
 }
 
 /// The start of the ore cost for obsidian robots.
@@ -71,62 +128,65 @@ const GEODE_START_ORE: usize = " Each geode robot costs ".len();
 /// The start of the obsidian cost for geode robots.
 const GEODE_START_OBSIDIAN: usize = GEODE_START_ORE + 1 + " ore and ".len();
 
-/// A global cache for recursive calls.
-static mut CACHE: OnceCell<HashMap<String, i32>> = OnceCell::new();
-
-/// Helper function to query the cache.
-fn check_cache(key: &str) -> Option<i32> {
-    unsafe { CACHE.get().unwrap().get(key).copied() }
-}
-
-/// Helper function to update the cache.
-fn update_cache(key: String, value: i32) {
-    unsafe {
-        CACHE.get_mut().unwrap().insert(key, value);
-    }
-}
+/// The state of the search the memo is keyed on - the minutes left, the robots and the storage.
+type State = (i32, Counts, Storage);
 
 /// Helper function to extract an integer from a string.
-fn get_int(string: &str) -> i32 {
+fn get_int(string: &str) -> Result<i32, String> {
     string
         .chars()
-        .filter(|char| char.is_digit(10))
+        .filter(|char| char.is_ascii_digit())
         .collect::<String>()
         .parse()
-        .unwrap()
+        .map_err(|_| format!("Expected a number in \"{}\"", string.trim()))
 }
 
-impl Blueprint {
+impl FromStr for Blueprint {
+    type Err = String;
+
     /// Parse a new blueprint from a blueprint line.
-    pub fn new(line: &str) -> Self {
-        let mut costs = line.split(":").skip(1).next().unwrap().split(".");
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut costs = line
+            .split(":")
+            .nth(1)
+            .ok_or("Missing \":\" after the blueprint number")?
+            .split(".");
+        let mut next_cost = |robot: &str| {
+            costs
+                .next()
+                .filter(|cost| cost.contains(robot))
+                .ok_or(format!("Missing the {robot} robot cost"))
+        };
 
-        let ore = get_int(costs.next().unwrap());
+        let ore = get_int(next_cost("ore")?)?;
 
-        let clay = get_int(costs.next().unwrap());
+        let clay = get_int(next_cost("clay")?)?;
 
-        let obsidian_str = costs.next().unwrap();
-        let obsidian_ore = get_int(obsidian_str.get(..OBSIDIAN_START_ORE + 2).unwrap());
-        let obsidian_clay = get_int(obsidian_str.get(OBSIDIAN_START_CLAY..).unwrap());
+        let obsidian_str = next_cost("obsidian")?;
+        let obsidian_ore = get_int(obsidian_str.get(..OBSIDIAN_START_ORE + 2).unwrap_or(""))?;
+        let obsidian_clay = get_int(obsidian_str.get(OBSIDIAN_START_CLAY..).unwrap_or(""))?;
 
-        let geode_str = costs.next().unwrap();
-        let geode_ore = get_int(geode_str.get(..GEODE_START_ORE + 2).unwrap());
-        let geode_obsidian = get_int(geode_str.get(GEODE_START_OBSIDIAN..).unwrap());
+        let geode_str = next_cost("geode")?;
+        let geode_ore = get_int(geode_str.get(..GEODE_START_ORE + 2).unwrap_or(""))?;
+        let geode_obsidian = get_int(geode_str.get(GEODE_START_OBSIDIAN..).unwrap_or(""))?;
 
-        Self {
+        Ok(Self {
             ore,
             clay,
             obsidian: (obsidian_ore, obsidian_clay),
             geode: (geode_ore, geode_obsidian),
-            // Find the max spend for each resource type.
-            max_spend: HashMap::from_iter([
-                (Robot::Ore, ore.max(clay).max(obsidian_ore).max(geode_ore)),
-                (Robot::Clay, obsidian_clay),
-                (Robot::Obsidian, geode_obsidian),
-            ]),
-        }
+            // Find the max spend for each resource type. There is no limit on geodes.
+            max_spend: [
+                ore.max(clay).max(obsidian_ore).max(geode_ore),
+                obsidian_clay,
+                geode_obsidian,
+                i32::MAX,
+            ],
+        })
     }
+}
 
+impl Blueprint {
     /// Get the ore cost for a robot type.
     fn get_ore_cost(&self, robot: &Robot) -> i32 {
         match robot {
@@ -140,225 +200,920 @@ impl Blueprint {
     /// Calculate the time needed to wait to build a given robot type. If no robots that build the
     /// resources required for this robots creation exist return None. Otherwise return the number
     /// of minutes before we are able to create a robot of the given type.
-    fn time_to_next_robot(
-        &self,
-        robot: &Robot,
-        robots: &HashMap<Robot, i32>,
-        storage: &Storage,
-    ) -> Option<i32> {
-        let ore_cost = self.get_ore_cost(&robot);
-        let Some(count) = robots.get(&Robot::Ore) else {
+    fn time_to_next_robot(&self, robot: &Robot, robots: &Counts, storage: &Storage) -> Option<i32> {
+        let ore_cost = self.get_ore_cost(robot);
+        let count = robots[Robot::Ore.index()];
+        if count == 0 {
             return None;
-        };
+        }
 
-        let ore_time = 0.max((ore_cost - storage.ore + *count - 1) / *count);
+        let ore_time = 0.max((ore_cost - storage[Robot::Ore] + count - 1) / count);
 
         match robot {
             Robot::Ore | Robot::Clay => Some(ore_time),
             Robot::Obsidian => {
-                let Some(count) = robots.get(&Robot::Clay) else {
-                  return None;
-                };
+                let count = robots[Robot::Clay.index()];
+                if count == 0 {
+                    return None;
+                }
 
-                Some(ore_time.max((self.obsidian.1 - storage.clay + *count - 1) / *count))
+                Some(ore_time.max((self.obsidian.1 - storage[Robot::Clay] + count - 1) / count))
             }
             Robot::Geode => {
-                let Some(count) = robots.get(&Robot::Obsidian) else {
+                let count = robots[Robot::Obsidian.index()];
+                if count == 0 {
                     return None;
-                };
+                }
 
-                Some(ore_time.max((self.geode.1 - storage.obsidian + *count - 1) / *count))
+                Some(ore_time.max((self.geode.1 - storage[Robot::Obsidian] + count - 1) / count))
             }
         }
     }
 
+    /// Check if we still need more robots of the given type. Geode robots are always worth
+    /// building. Other robots are only needed while the robots we have, together with the
+    /// resources in storage, can't cover spending the most we could spend of their resource in
+    /// each of the remaining minutes.
+    fn should_build(
+        &self,
+        robot: &Robot,
+        robots: &Counts,
+        storage: &Storage,
+        minutes_left: i32,
+    ) -> bool {
+        if robot == &Robot::Geode {
+            return true;
+        }
+
+        let index = robot.index();
+
+        robots[index] < self.max_spend[index]
+            && robots[index] * minutes_left + storage[*robot] < self.max_spend[index] * minutes_left
+    }
+
     /// Remove any extra robots. We consider robots that build more resources than we can use
     /// in a single turn to be extra robots.
-    fn remove_extra_robots(&self, robots: &mut HashMap<Robot, i32>) {
-        for (robot, count) in robots
-            .iter_mut()
-            .filter(|(robot, _)| robot != &&Robot::Geode)
-        {
-            *count = (*count).min(*self.max_spend.get(robot).unwrap_or(&0));
+    fn remove_extra_robots(&self, robots: &mut Counts) {
+        for (count, spend) in robots.iter_mut().zip(self.max_spend) {
+            *count = (*count).min(spend);
         }
     }
 
     /// Remove any extra resources. We consider resources that have more units than we can spend in
     /// the remaining turns to be extra resources.
-    fn remove_extra_resources(
-        &self,
-        robots: &HashMap<Robot, i32>,
-        storage: &mut Storage,
-        iterations: i32,
-    ) {
-        self.max_spend
-            .iter()
-            .for_each(|(ore_type, spend)| match ore_type {
-                Robot::Ore => {
-                    storage.ore = storage.ore.min(
-                        (*spend * iterations)
-                            - (iterations - 1) * robots.get(ore_type).unwrap_or(&0),
-                    )
-                }
-                Robot::Clay => {
-                    storage.clay = storage.clay.min(
-                        (*spend * iterations)
-                            - (iterations - 1) * robots.get(ore_type).unwrap_or(&0),
-                    )
-                }
-                Robot::Obsidian => {
-                    storage.obsidian = storage.obsidian.min(
-                        (*spend * iterations)
-                            - (iterations - 1) * robots.get(ore_type).unwrap_or(&0),
-                    )
-                }
-                Robot::Geode => panic!("Shouldn't happen"),
-            });
+    fn remove_extra_resources(&self, robots: &Counts, storage: &mut Storage, iterations: i32) {
+        // The most we could still spend of a resource, less what the robots will gather.
+        let limit =
+            |index: usize| self.max_spend[index] * iterations - (iterations - 1) * robots[index];
+
+        for robot in [Robot::Ore, Robot::Clay, Robot::Obsidian] {
+            storage[robot] = storage[robot].min(limit(robot.index()));
+        }
     }
 
     /// Pay for a robot creation. We decrease the amount of resources in storage based on the robot
     /// type and it's cost according to the blueprint.
     fn pay_for_robot(&self, storage: &mut Storage, robot: &Robot) {
         match robot {
-            Robot::Ore => storage.ore -= self.ore,
-            Robot::Clay => storage.ore -= self.clay,
+            Robot::Ore => storage[Robot::Ore] -= self.ore,
+            Robot::Clay => storage[Robot::Ore] -= self.clay,
             Robot::Obsidian => {
-                storage.ore -= self.obsidian.0;
-                storage.clay -= self.obsidian.1;
+                storage[Robot::Ore] -= self.obsidian.0;
+                storage[Robot::Clay] -= self.obsidian.1;
             }
             Robot::Geode => {
-                storage.ore -= self.geode.0;
-                storage.obsidian -= self.geode.1;
+                storage[Robot::Ore] -= self.geode.0;
+                storage[Robot::Obsidian] -= self.geode.1;
             }
         }
     }
 
+    /// Check if there are enough resources in storage to pay for a robot.
+    fn can_afford(&self, storage: &Storage, robot: &Robot) -> bool {
+        match robot {
+            Robot::Ore => storage[Robot::Ore] >= self.ore,
+            Robot::Clay => storage[Robot::Ore] >= self.clay,
+            Robot::Obsidian => {
+                storage[Robot::Ore] >= self.obsidian.0 && storage[Robot::Clay] >= self.obsidian.1
+            }
+            Robot::Geode => {
+                storage[Robot::Ore] >= self.geode.0 && storage[Robot::Obsidian] >= self.geode.1
+            }
+        }
+    }
+
+    /// Play out the given number of minutes from the given robots and storage greedily by building
+    /// a geode robot if we can afford it, otherwise the most expensive robot we can afford which
+    /// is still useful, otherwise waiting. This gives us a quick lower bound for the number of
+    /// geodes, which never exceeds the result of the full search.
+    fn greedy_geodes(&self, minutes: i32, robots: &Counts, storage: &Storage) -> i32 {
+        let mut robots = *robots;
+        let mut storage = *storage;
+
+        for _ in 0..minutes {
+            // Find the most expensive robot we can afford, skipping the robot types which already
+            // produce as much as we could ever spend in a minute.
+            let robot = Robot::all_types()
+                .into_iter()
+                .rev()
+                .filter(|robot| robots[robot.index()] < self.max_spend[robot.index()])
+                .find(|robot| self.can_afford(&storage, robot));
+
+            if let Some(robot) = &robot {
+                self.pay_for_robot(&mut storage, robot);
+            }
+
+            // Gather the resources with the robots we had at the start of the minute.
+            storage.gather(&robots, 1);
+
+            if let Some(robot) = robot {
+                robots[robot.index()] += 1;
+            }
+        }
+
+        storage[Robot::Geode]
+    }
+
+    /// Find the largest amount of geodes for each time budget from one minute up to `minutes`.
+    /// The memo is keyed on the minutes left, so the searches for the longer budgets reuse the
+    /// states already seen by the shorter ones. An extra minute never means fewer geodes, so the
+    /// best amount found carries over to the next budget and never decreases while the memo lives.
+    fn geodes_over_time(&self, minutes: i32) -> Vec<i32> {
+        let robots = STARTING_ROBOTS;
+        let storage = Storage::new();
+        let mut memo = Memo::new();
+        let mut best = 0;
+
+        (1..=minutes)
+            .map(|minutes| {
+                best = best.max(self.greedy_geodes(minutes, &robots, &storage));
+
+                self.max_geodes(&mut memo, minutes, &robots, &storage, &mut best)
+            })
+            .collect()
+    }
+
+    /// Get an optimistic bound on the amount of geodes we could crack in the remaining minutes -
+    /// the geodes in storage, the geodes our current geode robots crack, and the geodes cracked by
+    /// a new geode robot built every remaining minute.
+    fn geode_upper_bound(&self, minutes_left: i32, robots: &Counts, storage: &Storage) -> i32 {
+        storage[Robot::Geode]
+            + robots[Robot::Geode.index()] * minutes_left
+            + minutes_left * (minutes_left - 1) / 2
+    }
+
+    /// Find the largest amount of geodes we could crack in the given minutes starting with the
+    /// given robots and storage.
+    fn max_geodes_from(&self, minutes: i32, robots: &Counts, storage: &Storage) -> i32 {
+        // Start the search with the greedy result as the best so far.
+        let mut best = self.greedy_geodes(minutes, robots, storage);
+
+        self.max_geodes(&mut Memo::new(), minutes, robots, storage, &mut best)
+    }
+
+    /// Follow the decision chain which brings us the largest amount of geodes in the given minutes
+    /// and log each robot we build along with the robots and resources we have afterwards. At each
+    /// step we wait until the end if that already cracks the largest amount of geodes, otherwise we
+    /// pick the most expensive robot whose search result keeps the largest amount of geodes the
+    /// same, so no robot which doesn't help is logged.
+    fn trace_best(&self, minutes: i32) -> Vec<String> {
+        let mut memo = Memo::new();
+        let mut best = self.greedy_geodes(minutes, &STARTING_ROBOTS, &Storage::new());
+        let mut log = vec![];
+
+        // The search state, with extra robots and resources removed, to look up in the memo.
+        let (mut robots, mut storage) = (STARTING_ROBOTS, Storage::new());
+        // The robots and resources we actually have.
+        let (mut actual_robots, mut actual_storage) = (STARTING_ROBOTS, Storage::new());
+        let mut minutes_left = minutes;
+
+        loop {
+            let geodes = self.max_geodes(&mut memo, minutes_left, &robots, &storage, &mut best);
+
+            // Waiting until the end cracks the geodes in storage and the ones our geode robots crack.
+            let waiting_geodes =
+                storage[Robot::Geode] + robots[Robot::Geode.index()] * minutes_left;
+
+            // Find the robot to build next which still leads to the largest amount of geodes.
+            let next = Robot::all_types().into_iter().rev().find_map(|robot| {
+                if waiting_geodes == geodes {
+                    return None;
+                }
+
+                if !self.should_build(&robot, &robots, &storage, minutes_left) {
+                    return None;
+                }
+
+                let wait_time = self.time_to_next_robot(&robot, &robots, &storage)?;
+                let remaining_time = minutes_left - wait_time - 1;
+
+                if remaining_time <= 0 {
+                    return None;
+                }
+
+                let mut next_storage = storage;
+                next_storage.gather(&robots, wait_time + 1);
+                self.pay_for_robot(&mut next_storage, &robot);
+
+                let mut next_robots = robots;
+                next_robots[robot.index()] += 1;
+                self.remove_extra_robots(&mut next_robots);
+                self.remove_extra_resources(&next_robots, &mut next_storage, remaining_time);
+
+                let next_geodes = self.max_geodes(
+                    &mut memo,
+                    remaining_time,
+                    &next_robots,
+                    &next_storage,
+                    &mut best,
+                );
+
+                (next_geodes == geodes).then_some((robot, wait_time, next_robots, next_storage))
+            });
+
+            // If no robot leads to more geodes, we wait until the end.
+            let Some((robot, wait_time, next_robots, next_storage)) = next else {
+                actual_storage.gather(&actual_robots, minutes_left);
+                log.push(format!(
+                    "Minute {minutes}: wait until the end with {actual_storage}"
+                ));
+
+                return log;
+            };
+
+            actual_storage.gather(&actual_robots, wait_time + 1);
+            self.pay_for_robot(&mut actual_storage, &robot);
+            actual_robots[robot.index()] += 1;
+            minutes_left -= wait_time + 1;
+            (robots, storage) = (next_robots, next_storage);
+
+            let article = match robot {
+                Robot::Ore | Robot::Obsidian => "an",
+                Robot::Clay | Robot::Geode => "a",
+            };
+
+            log.push(format!(
+                "Minute {}: build {article} {robot:?} robot, then have {} ore, {} clay, {} obsidian and {} \
+                 geode robots and {actual_storage}",
+                minutes - minutes_left,
+                actual_robots[0],
+                actual_robots[1],
+                actual_robots[2],
+                actual_robots[3],
+            ));
+        }
+    }
+
     /// Recursively search for the decision chain which would bring us the largest amount of
-    /// geodes.
+    /// geodes. Keep the best amount of geodes found so far up to date in `best`. Already computed
+    /// states are looked up in `memo`.
+    ///
+    /// Branches whose optimistic bound is below `best` are pruned, so a state which can't reach
+    /// `best` may get a lower amount than it could actually crack. Such an amount is still below
+    /// `best`, so it is only safe to reuse the memo as long as `best` never decreases. Branches
+    /// which could tie `best` are kept, so every state on a best decision chain gets its exact
+    /// amount, which `trace_best` relies on.
     fn max_geodes(
         &self,
+        memo: &mut Memo<State, i32>,
         minutes_left: i32,
-        robots: &HashMap<Robot, i32>,
+        robots: &Counts,
         storage: &Storage,
+        best: &mut i32,
     ) -> i32 {
+        profile::record_call();
+
         // If there is no time left we return the number of geodes we have in storage.
         if minutes_left == 0 {
-            return storage.geode;
+            return storage[Robot::Geode];
         }
 
-        // Create a key for the cache based on current parameters.
-        let key = format!("{minutes_left}:{self:?}+{robots:?}+{storage:?}");
+        // Create a key for the memo based on current parameters. The memo is only used for a
+        // single blueprint, so it doesn't need to be part of the key.
+        let key = (minutes_left, *robots, *storage);
 
-        // If there is a cache hit we return the value from the cache.
-        if let Some(result) = check_cache(&key) {
-            return result;
-        }
+        // If there is a memo hit we return the value from the memo, otherwise we compute it.
+        memo.get_or_compute(key, |memo| {
+            let mut max_geodes = storage[Robot::Geode];
 
-        let mut max_geodes = storage.geode;
+            // Increase the assumed number of max geodes by the amount of geodes the current geode
+            // robots would produce in the remaining time.
+            max_geodes += robots[Robot::Geode.index()] * minutes_left;
 
-        // Increase the assumed number of max geodes by the amount of geodes the current geode
-        // robots would produce in the remaining time.
-        if let Some(geode_robots) = robots.get(&Robot::Geode) {
-            max_geodes += geode_robots * minutes_left;
-        }
+            // Waiting until the end is always possible, so this is a result we could achieve.
+            *best = (*best).max(max_geodes);
 
-        // Iterate through all robot types.
-        for robot_type in Robot::all_types() {
-            // If the robot type count is larger than the max amount we could spend we just ignore
-            // this path.
-            if let (Some(spend), Some(count)) =
-                (self.max_spend.get(&robot_type), robots.get(&robot_type))
-            {
-                if count >= spend {
+            // Iterate through all robot types.
+            for robot_type in Robot::all_types() {
+                // If we already have enough of this robot type for the rest of the time we just
+                // ignore this path.
+                if !self.should_build(&robot_type, robots, storage, minutes_left) {
                     continue;
                 }
-            }
 
-            // If there is not time we could wait to build a robot of this type we skip this path,
-            // otherwise we record the time we would wait.
-            let Some(wait_time) = self.time_to_next_robot(&robot_type, robots, storage) else {
-                continue;
-            };
+                // If there is not time we could wait to build a robot of this type we skip this path,
+                // otherwise we record the time we would wait.
+                let Some(wait_time) = self.time_to_next_robot(&robot_type, robots, storage) else {
+                    continue;
+                };
 
-            let remaining_time = minutes_left - wait_time - 1;
+                let remaining_time = minutes_left - wait_time - 1;
 
-            // If time leftover after the robot creation is zero or less, we ignore this path.
-            if remaining_time <= 0 {
-                continue;
-            }
+                // If time leftover after the robot creation is zero or less, we ignore this path.
+                if remaining_time <= 0 {
+                    continue;
+                }
 
-            let mut storage_clone = storage.clone();
+                let mut storage_clone = *storage;
 
-            // Gather the resources with the current robots.
-            storage_clone.gather(robots, wait_time + 1);
+                // Gather the resources with the current robots.
+                storage_clone.gather(robots, wait_time + 1);
 
-            // Pay for the robot creation.
-            self.pay_for_robot(&mut storage_clone, &robot_type);
+                // Pay for the robot creation.
+                self.pay_for_robot(&mut storage_clone, &robot_type);
 
-            let mut robots_clone = robots.clone();
+                let mut robots_clone = *robots;
 
-            // Add the robot to our robots map.
-            robots_clone
-                .entry(robot_type)
-                .and_modify(|count| *count += 1)
-                .or_insert(1);
+                // Add the robot to our robot counts.
+                robots_clone[robot_type.index()] += 1;
 
-            // Remove any extra robots.
-            self.remove_extra_robots(&mut robots_clone);
+                // Remove any extra robots.
+                self.remove_extra_robots(&mut robots_clone);
 
-            // Remove any extra resources.
-            self.remove_extra_resources(&robots_clone, &mut storage_clone, remaining_time);
+                // Remove any extra resources.
+                self.remove_extra_resources(&robots_clone, &mut storage_clone, remaining_time);
 
-            // Find the max geodes we could build in the remaining time.
-            max_geodes =
-                max_geodes.max(self.max_geodes(remaining_time, &robots_clone, &storage_clone));
-        }
+                // If even building a geode robot every minute from here on can't reach the best
+                // amount of geodes found so far, we ignore this path.
+                if self.geode_upper_bound(remaining_time, &robots_clone, &storage_clone) < *best {
+                    continue;
+                }
+
+                // Find the max geodes we could build in the remaining time.
+                max_geodes = max_geodes.max(self.max_geodes(
+                    memo,
+                    remaining_time,
+                    &robots_clone,
+                    &storage_clone,
+                    best,
+                ));
+            }
 
-        // Update the cache with the new result.
-        update_cache(key, max_geodes);
+            *best = (*best).max(max_geodes);
 
-        max_geodes
+            max_geodes
+        })
     }
 }
 
-/// Read the blueprints from a given input file into a vector.
-fn get_blueprints(filename: &str) -> Vec<Blueprint> {
-    std::fs::read_to_string(filename)
-        .unwrap()
-        .lines()
-        .map(|line| Blueprint::new(line))
+/// Parse the blueprints from the input into a vector. A blueprint spans from one
+/// `Blueprint ` marker until the next one, so blueprints can be wrapped over several lines or
+/// separated by blank lines. Returns an error with the line number of the first blueprint that
+/// couldn't be parsed.
+fn parse_blueprints(input: &str) -> Result<Vec<Blueprint>, ParseError> {
+    // Find where each of the blueprints starts.
+    let starts = input
+        .match_indices("Blueprint ")
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+
+    // Make sure there is nothing but whitespace before the first blueprint.
+    let first_start = starts.first().copied().unwrap_or(input.len());
+    if !input[..first_start].trim().is_empty() {
+        return Err(ParseError::new(
+            1,
+            "Expected the input to start with a blueprint",
+        ));
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, &start)| {
+            let end = starts.get(index + 1).copied().unwrap_or(input.len());
+
+            // Join the wrapped lines of the blueprint into a single line.
+            let blueprint = input
+                .get(start..end)
+                .unwrap()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            blueprint.parse().map_err(|error| {
+                let line = input.get(..start).unwrap().lines().count() + 1;
+
+                ParseError::new(line, error)
+            })
+        })
         .collect()
 }
 
+/// Get the blueprint with the given id, starting at 1, or the first blueprint if no id is given.
+fn get_blueprint(blueprints: &[Blueprint], id: Option<String>) -> Result<&Blueprint, String> {
+    let Some(id) = id else {
+        return blueprints
+            .first()
+            .ok_or_else(|| "There are no blueprints".to_string());
+    };
+
+    id.parse::<usize>()
+        .ok()
+        .filter(|id| (1..=blueprints.len()).contains(id))
+        .map(|id| &blueprints[id - 1])
+        .ok_or_else(|| {
+            format!(
+                "Invalid blueprint id \"{id}\", expected 1 to {}",
+                blueprints.len()
+            )
+        })
+}
+
+/// Lazily find the maximum number of geodes each blueprint can crack in the given minutes,
+/// starting with the given robots and an empty storage. Yields the blueprint ids, starting at 1,
+/// along with the geodes in the order of the blueprints.
+fn quality_levels<'a>(
+    blueprints: &'a [Blueprint],
+    minutes: i32,
+    starting_robots: &'a Counts,
+) -> impl Iterator<Item = (usize, i32)> + 'a {
+    blueprints
+        .iter()
+        .enumerate()
+        .map(move |(index, blueprint)| {
+            (
+                index + 1,
+                blueprint.max_geodes_from(minutes, starting_robots, &Storage::new()),
+            )
+        })
+}
+
+/// Parse the comma separated ore, clay, obsidian and geode robot counts to start with. None of
+/// the counts can be negative.
+fn parse_starting_robots(counts: &str) -> Result<Counts, String> {
+    let counts = counts
+        .split(",")
+        .map(|count| match count.parse::<i32>() {
+            Ok(count) if count < 0 => Err(format!("Robot counts can't be negative, got {count}")),
+            Ok(count) => Ok(count),
+            Err(error) => Err(format!("Invalid robot count \"{count}\": {error}")),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    counts.try_into().map_err(|counts: Vec<i32>| {
+        format!(
+            "Expected 4 robot counts for --start-robots, got {}",
+            counts.len()
+        )
+    })
+}
+
+/// Read the starting robots from the `--start-robots` flag, defaulting to the puzzle's single ore
+/// robot.
+fn get_starting_robots() -> Result<Counts, String> {
+    let Some(counts) = flag_value("--start-robots") else {
+        return Ok(STARTING_ROBOTS);
+    };
+
+    parse_starting_robots(
+        &counts.ok_or_else(|| "The --start-robots flag expects robot counts".to_string())?,
+    )
+}
+
 fn main() {
     // Get the blueprints.
-    let blueprints = get_blueprints("input.txt");
+    let input = match read_input(input_filename()) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    let blueprints = match parse_blueprints(&input) {
+        Ok(blueprints) => blueprints,
+        Err(error) => {
+            eprintln!("{}", report_error(&error, &input, verbose()));
+            std::process::exit(1);
+        }
+    };
+
+    // Only check that the input is valid if requested.
+    if std::env::args().any(|arg| arg == "--validate") {
+        println!("ok, {} blueprints", blueprints.len());
+        return;
+    }
+
+    // Print the geodes for each time budget of a single blueprint if requested.
+    if let Some(id) = flag_value("--over-time").filter(|_| !answers_only()) {
+        let blueprint = match get_blueprint(&blueprints, id) {
+            Ok(blueprint) => blueprint,
+            Err(error) => {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+        };
+
+        for (minutes, geodes) in blueprint.geodes_over_time(32).iter().enumerate() {
+            println!("{}: {geodes}", minutes + 1);
+        }
 
-    // Initialize the starting values.
-    let starting_robots = HashMap::from_iter([(Robot::Ore, 1)]);
-    let storage = Storage::new();
-    unsafe {
-        CACHE.set(HashMap::new()).unwrap();
+        return;
     }
 
+    // Print the best decision chain of a single blueprint in 24 minutes if requested.
+    if let Some(id) = flag_value("--trace").filter(|_| !answers_only()) {
+        let blueprint = match get_blueprint(&blueprints, id) {
+            Ok(blueprint) => blueprint,
+            Err(error) => {
+                eprintln!("{error}");
+                std::process::exit(1);
+            }
+        };
+
+        println!("{}", blueprint.trace_best(24).join("\n"));
+
+        return;
+    }
+
+    // Get the robots to start with.
+    let starting_robots = match get_starting_robots() {
+        Ok(starting_robots) => starting_robots,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+
     // Sum the quality levels of each blueprint.
-    let quality_levels = blueprints
-        .iter()
-        .enumerate()
-        .map(|(index, blueprint)| {
-            blueprint.max_geodes(24, &starting_robots.clone(), &storage.clone())
-                * (index + 1) as i32
-        })
+    let quality_levels_sum = quality_levels(&blueprints, 24, &starting_robots)
+        .map(|(id, geodes)| id as i32 * geodes)
         .sum::<i32>();
 
-    println!("{quality_levels}");
-
     // Calculate the product of the first three blueprints' maximum geodes cracked.
-    let first_three_product = blueprints
-        .iter()
-        .take(3)
-        .map(|blueprint| blueprint.max_geodes(32, &starting_robots.clone(), &storage.clone()))
-        .product::<i32>();
+    let first_three_product =
+        quality_levels(&blueprints[..blueprints.len().min(3)], 32, &starting_robots)
+            .map(|(_, geodes)| geodes)
+            .product::<i32>();
+
+    // Print the search counters when profiling.
+    #[cfg(feature = "profile")]
+    profile::print_summary();
+
+    print_answers(19, quality_levels_sum, first_three_product);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = include_str!("../sample.txt");
+
+    #[test]
+    fn profile_counts_calls_and_cache_hits() {
+        let blueprints = parse_blueprints(EXAMPLE).unwrap();
+
+        profile::reset();
+        assert_eq!(
+            blueprints[0].max_geodes_from(24, &STARTING_ROBOTS, &Storage::new()),
+            9
+        );
+        let counters = profile::counters();
+
+        assert!(counters.calls > 0);
+        assert!(counters.cache_hits > 0);
+        assert!(counters.cache_size > 0);
+    }
+
+    #[test]
+    fn trace_follows_the_known_best_build_order() {
+        let blueprints = parse_blueprints(EXAMPLE).unwrap();
+
+        let trace = blueprints[0].trace_best(24);
+        let builds = trace
+            .iter()
+            .filter_map(|line| {
+                let (minute, build) = line.split_once(": build ")?;
+                let robot = build.split_whitespace().nth(1)?;
+
+                Some(format!("{} {robot}", minute.trim_start_matches("Minute ")))
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            builds,
+            [
+                "3 Clay",
+                "5 Clay",
+                "7 Clay",
+                "11 Obsidian",
+                "12 Clay",
+                "15 Obsidian",
+                "18 Geode",
+                "21 Geode"
+            ]
+        );
+        assert!(trace.last().unwrap().ends_with(", 9 geode"));
+    }
+
+    #[test]
+    fn greedy_seed_keeps_the_example_answers() {
+        let blueprints = parse_blueprints(EXAMPLE).unwrap();
+
+        for (blueprint, geodes) in blueprints.iter().zip([9, 12]) {
+            let greedy = blueprint.greedy_geodes(24, &STARTING_ROBOTS, &Storage::new());
+            assert!(greedy <= geodes);
+
+            // Seeding the best amount with the greedy result gives the same answer as starting
+            // from nothing.
+            let mut best = 0;
+            let unseeded = blueprint.max_geodes(
+                &mut Memo::new(),
+                24,
+                &STARTING_ROBOTS,
+                &Storage::new(),
+                &mut best,
+            );
+            assert_eq!(unseeded, geodes);
+            assert_eq!(best, geodes);
+
+            assert_eq!(
+                blueprint.max_geodes_from(24, &STARTING_ROBOTS, &Storage::new()),
+                geodes
+            );
+        }
+    }
+
+    #[test]
+    fn wrapped_blueprints_parse_like_single_lines() {
+        let blueprints = parse_blueprints(EXAMPLE).unwrap();
+
+        // Wrap each blueprint after its ore robot cost and separate them with a blank line.
+        let wrapped = EXAMPLE
+            .lines()
+            .map(|line| line.replacen(" Each clay", "\n  Each clay", 1))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        assert_eq!(wrapped.lines().count(), 5);
+
+        assert_eq!(parse_blueprints(&wrapped), Ok(blueprints));
+        assert_eq!(
+            parse_blueprints("Each ore robot costs 4 ore."),
+            Err(ParseError::new(
+                1,
+                "Expected the input to start with a blueprint"
+            ))
+        );
+    }
+
+    #[test]
+    fn geodes_never_decrease_with_more_time() {
+        let blueprints = parse_blueprints(EXAMPLE).unwrap();
+
+        let geodes = blueprints[0].geodes_over_time(24);
+
+        assert_eq!(geodes.len(), 24);
+        assert!(geodes.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(geodes[..18], [0; 18]);
+        assert_eq!(geodes[23], 9);
+    }
+
+    #[test]
+    fn array_gather_matches_a_map_of_robots() {
+        use std::collections::HashMap;
 
-    println!("{first_three_product}");
+        for robots in [[1, 0, 0, 0], [2, 3, 1, 0], [4, 7, 5, 2]] {
+            let map = Robot::all_types()
+                .into_iter()
+                .zip(robots)
+                .collect::<HashMap<_, _>>();
+
+            for minutes in [1, 3, 24] {
+                let mut storage = Storage::new();
+                storage.gather(&robots, minutes);
+
+                for (robot, count) in &map {
+                    assert_eq!(storage[*robot], count * minutes);
+                }
+            }
+        }
+
+        let blueprints = parse_blueprints(EXAMPLE).unwrap();
+        let geodes = quality_levels(&blueprints, 24, &STARTING_ROBOTS)
+            .map(|(_, geodes)| geodes)
+            .collect::<Vec<_>>();
+        assert_eq!(geodes, [9, 12]);
+    }
+
+    #[test]
+    fn quality_levels_yield_ids_in_order() {
+        let blueprints = parse_blueprints(EXAMPLE).unwrap();
+
+        assert_eq!(
+            quality_levels(&blueprints, 24, &STARTING_ROBOTS).collect::<Vec<_>>(),
+            [(1, 9), (2, 12)]
+        );
+
+        // Only the blueprints which are asked for are searched, so taking the first one makes as
+        // many search calls as searching the first blueprint on its own.
+        profile::reset();
+        let mut levels = quality_levels(&blueprints, 24, &STARTING_ROBOTS);
+        assert_eq!(levels.next(), Some((1, 9)));
+        let calls = profile::counters().calls;
+
+        profile::reset();
+        blueprints[0].max_geodes_from(24, &STARTING_ROBOTS, &Storage::new());
+        assert_eq!(profile::counters().calls, calls);
+    }
+
+    #[test]
+    fn greedy_never_beats_the_search() {
+        let blueprints = parse_blueprints(EXAMPLE).unwrap();
+
+        for blueprint in &blueprints {
+            for minutes in [1, 10, 20, 24] {
+                let greedy = blueprint.greedy_geodes(minutes, &STARTING_ROBOTS, &Storage::new());
+                let best = blueprint.max_geodes_from(minutes, &STARTING_ROBOTS, &Storage::new());
+
+                assert!(greedy <= best, "{greedy} > {best} in {minutes} minutes");
+            }
+        }
+    }
+
+    #[test]
+    fn storage_index_reads_and_writes_each_field() {
+        let mut storage = Storage::new();
+
+        for (amount, robot) in (1..).zip(Robot::all_types()) {
+            storage[robot] += amount;
+        }
+
+        assert_eq!(
+            storage,
+            Storage {
+                ore: 1,
+                clay: 2,
+                obsidian: 3,
+                geode: 4,
+            }
+        );
+        assert_eq!(
+            Robot::all_types()
+                .into_iter()
+                .map(|robot| storage[robot])
+                .collect::<Vec<_>>(),
+            [1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn extra_starting_robots_never_hurt() {
+        let blueprints = parse_blueprints(EXAMPLE).unwrap();
+        let blueprint = &blueprints[0];
+
+        let default = blueprint.max_geodes_from(24, &STARTING_ROBOTS, &Storage::new());
+        let extra_ore = blueprint.max_geodes_from(24, &[2, 0, 0, 0], &Storage::new());
+        let extra_clay = blueprint.max_geodes_from(24, &[1, 1, 0, 0], &Storage::new());
+        let extra_both = blueprint.max_geodes_from(24, &[2, 1, 0, 0], &Storage::new());
+
+        assert_eq!(default, 9);
+        assert!(extra_ore >= default);
+        assert!(extra_clay >= default);
+        assert!(extra_both >= extra_ore.max(extra_clay));
+
+        // Starting with resources in storage never hurts either.
+        let mut storage = Storage::new();
+        storage[Robot::Ore] = 4;
+        assert!(blueprint.max_geodes_from(24, &STARTING_ROBOTS, &storage) >= default);
+    }
+
+    /// The search before `should_build` took the remaining time into account - robots are only
+    /// skipped once we have as many as we could spend of their resource in a single minute.
+    fn max_geodes_without_time_bound(
+        blueprint: &Blueprint,
+        memo: &mut Memo<State, i32>,
+        minutes_left: i32,
+        robots: &Counts,
+        storage: &Storage,
+        best: &mut i32,
+    ) -> i32 {
+        profile::record_call();
+
+        if minutes_left == 0 {
+            return storage[Robot::Geode];
+        }
+
+        memo.get_or_compute((minutes_left, *robots, *storage), |memo| {
+            let mut max_geodes =
+                storage[Robot::Geode] + robots[Robot::Geode.index()] * minutes_left;
+            *best = (*best).max(max_geodes);
+
+            for robot in Robot::all_types() {
+                if robots[robot.index()] >= blueprint.max_spend[robot.index()] {
+                    continue;
+                }
+
+                let Some(wait_time) = blueprint.time_to_next_robot(&robot, robots, storage) else {
+                    continue;
+                };
+                let remaining_time = minutes_left - wait_time - 1;
+                if remaining_time <= 0 {
+                    continue;
+                }
+
+                let mut next_storage = *storage;
+                next_storage.gather(robots, wait_time + 1);
+                blueprint.pay_for_robot(&mut next_storage, &robot);
+
+                let mut next_robots = *robots;
+                next_robots[robot.index()] += 1;
+                blueprint.remove_extra_robots(&mut next_robots);
+                blueprint.remove_extra_resources(&next_robots, &mut next_storage, remaining_time);
+
+                if blueprint.geode_upper_bound(remaining_time, &next_robots, &next_storage) < *best
+                {
+                    continue;
+                }
+
+                max_geodes = max_geodes.max(max_geodes_without_time_bound(
+                    blueprint,
+                    memo,
+                    remaining_time,
+                    &next_robots,
+                    &next_storage,
+                    best,
+                ));
+            }
+
+            *best = (*best).max(max_geodes);
+
+            max_geodes
+        })
+    }
+
+    #[test]
+    fn should_build_prunes_calls_but_not_geodes() {
+        let blueprints = parse_blueprints(EXAMPLE).unwrap();
+
+        for (blueprint, geodes) in blueprints.iter().zip([9, 12]) {
+            profile::reset();
+            assert_eq!(
+                blueprint.max_geodes_from(24, &STARTING_ROBOTS, &Storage::new()),
+                geodes
+            );
+            let calls = profile::counters().calls;
+
+            profile::reset();
+            let mut best = blueprint.greedy_geodes(24, &STARTING_ROBOTS, &Storage::new());
+            assert_eq!(
+                max_geodes_without_time_bound(
+                    blueprint,
+                    &mut Memo::new(),
+                    24,
+                    &STARTING_ROBOTS,
+                    &Storage::new(),
+                    &mut best
+                ),
+                geodes
+            );
+            let calls_without_time_bound = profile::counters().calls;
+
+            assert!(
+                calls < calls_without_time_bound,
+                "{calls} calls, {calls_without_time_bound} without the time bound"
+            );
+        }
+    }
+
+    #[test]
+    fn blueprint_ids_are_checked() {
+        let blueprints = parse_blueprints(EXAMPLE).unwrap();
+
+        assert_eq!(get_blueprint(&blueprints, None), Ok(&blueprints[0]));
+        assert_eq!(
+            get_blueprint(&blueprints, Some("2".to_string())),
+            Ok(&blueprints[1])
+        );
+
+        for id in ["0", "3", "x", "-1"] {
+            assert_eq!(
+                get_blueprint(&blueprints, Some(id.to_string())),
+                Err(format!("Invalid blueprint id \"{id}\", expected 1 to 2"))
+            );
+        }
+        assert!(get_blueprint(&[], None).is_err());
+    }
+
+    #[test]
+    fn starting_robot_counts_cant_be_negative() {
+        assert_eq!(parse_starting_robots("2,1,0,0"), Ok([2, 1, 0, 0]));
+        assert_eq!(
+            parse_starting_robots("-1,0,0,0"),
+            Err("Robot counts can't be negative, got -1".to_string())
+        );
+        assert_eq!(
+            parse_starting_robots("1,0,0"),
+            Err("Expected 4 robot counts for --start-robots, got 3".to_string())
+        );
+        assert!(parse_starting_robots("1,x,0,0").is_err());
+    }
 }
@@ -0,0 +1,44 @@
+use std::process::{Command, Output};
+
+/// Run the solution with `--validate` from the given directory.
+fn validate(directory: &std::path::Path, flags: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_day-19"))
+        .current_dir(directory)
+        .arg("--validate")
+        .args(flags)
+        .output()
+        .expect("Couldn't run day-19")
+}
+
+#[test]
+fn validates_the_sample() {
+    let output = validate(env!("CARGO_MANIFEST_DIR").as_ref(), &["--example"]);
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "ok, 2 blueprints\n"
+    );
+}
+
+#[test]
+fn reports_the_first_bad_line() {
+    // Break a line of the sample and validate it as the input of a separate directory.
+    let sample = include_str!("../sample.txt");
+    let directory = std::env::temp_dir().join("day-19-bad-input");
+    std::fs::create_dir_all(&directory).unwrap();
+    std::fs::write(
+        directory.join("input.txt"),
+        sample.replacen("costs 3 ore and 14 clay", "costs 3 ore and clay", 1),
+    )
+    .unwrap();
+
+    let output = validate(&directory, &[]);
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Line 1: Expected a number in \"clay\"\n"
+    );
+}
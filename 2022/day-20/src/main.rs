@@ -1,71 +1,60 @@
-/// Mix the values of a given slice by moving each element by adding their value to their inedx.
-fn mix(coords: &[i64], rounds: u8) -> Vec<i64> {
-    // Create a vector that keeps track of the initial index of a value, the actual value, and the
-    // number of times it has moved so far.
+use aoc_common::{
+    answers_only, extra_output, input_filename, parse_lines, print_answers, read_input,
+    report_error, verbose,
+};
+
+/// Move a value at position `pos` by `offset` places in a circular list of length `len`. While a
+/// value moves it is taken out of the list, so the positions wrap around `len - 1` places.
+fn wrap_index(pos: i64, offset: i64, len: i64) -> usize {
+    if len <= 1 {
+        return 0;
+    }
+
+    (((pos + offset) % (len - 1) + (len - 1)) % (len - 1)) as usize
+}
+
+/// Mix the values of a given slice by moving each element by adding their value to their index.
+/// Returns the values in their final order along with the index each value had in the slice, so
+/// that equal values can be told apart.
+fn mix_with_origins(coords: &[i64], rounds: u8) -> Vec<(i64, usize)> {
+    // Create a vector that keeps track of the actual value and the initial index of a value.
     let mut new_coords = coords
         .iter()
         .enumerate()
-        .map(|(index, value)| (value, 0, index))
+        .map(|(index, value)| (*value, index))
         .collect::<Vec<_>>();
 
-    for round in 0..rounds {
+    for _ in 0..rounds {
         // Keep mixing in the same order as in the starting slice.
         for (index, value) in coords.iter().enumerate() {
             // Find the new index of the next value we want to move.
             let new_index = new_coords
                 .iter()
-                .enumerate()
-                .find_map(|(new_index, (old_value, moved, old_index))| {
-                    if old_value == &value && moved == &round && old_index == &index {
-                        Some(new_index)
-                    } else {
-                        None
-                    }
-                })
+                .position(|(_, old_index)| old_index == &index)
                 .unwrap();
 
             // Remove the value from the vector.
             let new_value = new_coords.remove(new_index);
-            // Add the value to the index.
-            let unbound_next = new_index as i64 + value;
-            // Capture new array length.
-            let array_len = new_coords.len() as i64;
-
-            // If the new index is negative we find division remainder and add array length to make it a
-            // valid index.
-            let next_index = if unbound_next < 0 {
-                array_len + unbound_next % array_len
-            // If the new index is greater than or equal to the array length we find the
-            // division remainder.
-            } else if unbound_next >= array_len {
-                unbound_next % array_len
-            // If the new index is the same as the old index, we decrease it by one because we took
-            // the element out.
-            } else if unbound_next == index as i64 {
-                unbound_next - 1
-            // Otherwise just use the same index.
-            } else {
-                unbound_next
-            } as usize;
+            // Find where the value lands among the remaining values.
+            let next_index = wrap_index(new_index as i64, *value, coords.len() as i64);
 
             // If the new index is zero, that means that the value goes at the end.
             if next_index == 0 {
-                new_coords.push((new_value.0, round + 1, new_value.2));
+                new_coords.push(new_value);
             } else {
-                new_coords.insert(next_index, (new_value.0, round + 1, new_value.2));
+                new_coords.insert(next_index, new_value);
             }
         }
     }
 
-    new_coords.into_iter().map(|(&value, ..)| value).collect()
+    new_coords
 }
 
-/// Read the input coordinates from the input file into a vector.
-fn read_input(filename: &str) -> Vec<i64> {
-    std::fs::read_to_string(filename)
-        .unwrap()
-        .lines()
-        .map(|line| line.parse().unwrap())
+/// Mix the values of a given slice and return just the values in their final order.
+fn mix(coords: &[i64], rounds: u8) -> Vec<i64> {
+    mix_with_origins(coords, rounds)
+        .into_iter()
+        .map(|(value, _)| value)
         .collect()
 }
 
@@ -85,9 +74,77 @@ fn get_coords(coords: &[i64]) -> i64 {
     coords[index_1_000] + coords[index_2_000] + coords[index_3_000]
 }
 
+/// Mix the values of a given slice by swapping each value with its neighbour one place at a time
+/// until it has moved as far as its value says. Moving a value a full lap of the other values
+/// brings it back to where it started, so only the remainder of the laps is walked. This is the
+/// slow reference `--validate-answers` checks the answers against.
+fn mix_reference(coords: &[i64], rounds: u8) -> Vec<i64> {
+    let len = coords.len();
+
+    if len <= 1 {
+        return coords.to_vec();
+    }
+
+    // Keep track of the original index of the value at each position.
+    let mut order = (0..len).collect::<Vec<_>>();
+
+    for _ in 0..rounds {
+        for (index, value) in coords.iter().enumerate() {
+            let mut position = order.iter().position(|origin| origin == &index).unwrap();
+            let steps = value.rem_euclid(len as i64 - 1);
+
+            for _ in 0..steps {
+                let next = (position + 1) % len;
+                order.swap(position, next);
+                position = next;
+            }
+        }
+    }
+
+    order.into_iter().map(|origin| coords[origin]).collect()
+}
+
+/// Get the answer to the first part using the slow reference mix.
+fn part1_reference(coords: &[i64]) -> i64 {
+    get_coords(&mix_reference(coords, 1))
+}
+
+/// Get the answer to the second part using the slow reference mix.
+fn part2_reference(coords: &[i64]) -> i64 {
+    get_coords(&mix_reference(
+        &coords
+            .iter()
+            .map(|coord| coord.checked_mul(811_589_153).unwrap())
+            .collect::<Vec<_>>(),
+        10,
+    ))
+}
+
 fn main() {
     // Get the coordinate encryption from the input file.
-    let coords = read_input("input.txt");
+    let input = match read_input(input_filename()) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    let coords = match parse_lines::<i64>(&input) {
+        Ok(coords) => coords,
+        Err(error) => {
+            eprintln!("{}", report_error(&error, &input, verbose()));
+            std::process::exit(1);
+        }
+    };
+
+    // Print the mixed coordinates along with their original indexes if requested.
+    if extra_output("--origins") {
+        for (value, index) in mix_with_origins(&coords, 1) {
+            println!("{value} (from {index})");
+        }
+
+        println!();
+    }
 
     // Mix the coordinates to decrypt them.
     let mixed = mix(&coords, 1);
@@ -95,8 +152,6 @@ fn main() {
     // Sum the 1_000-th, 2_000-th and 3_000-th values.
     let sum_coords = get_coords(&mixed);
 
-    println!("{sum_coords:?}");
-
     // Mix the coordinates ten times, and before that multiply them with the decryption key.
     let new_mixed = mix(
         &coords
@@ -107,7 +162,157 @@ fn main() {
     );
 
     // Sum the 1_000-th, 2_000-th and 3_000-th values.
-    let sum_coords = get_coords(&new_mixed);
+    let new_sum_coords = get_coords(&new_mixed);
+
+    // Check the answers against the slow reference implementations if requested.
+    if std::env::args().any(|arg| arg == "--validate-answers") {
+        let reference = (part1_reference(&coords), part2_reference(&coords));
+
+        if reference != (sum_coords, new_sum_coords) {
+            eprintln!(
+                "Answers {sum_coords} and {new_sum_coords} don't match the reference answers {} and {}",
+                reference.0, reference.1
+            );
+            std::process::exit(1);
+        }
+
+        if !answers_only() {
+            eprintln!("Answers match the reference implementation.");
+        }
+    }
+
+    print_answers(20, sum_coords, new_sum_coords);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Get the order of a circular list of `len` values after moving the value at `pos` by
+    /// `offset` places, swapping it with its neighbour one place at a time. The order is read
+    /// starting from the moved value, since where a circular list starts doesn't matter.
+    fn rotate_naively(len: usize, pos: usize, offset: i64) -> Vec<usize> {
+        let mut values = (0..len).collect::<Vec<_>>();
+        let mut position = pos;
+
+        for _ in 0..offset.unsigned_abs() {
+            let next = if offset > 0 {
+                (position + 1) % len
+            } else {
+                (position + len - 1) % len
+            };
+
+            values.swap(position, next);
+            position = next;
+        }
+
+        values.rotate_left(position);
+        values
+    }
+
+    /// Get the order of a circular list of `len` values after moving the value at `pos` by
+    /// `offset` places the way `mix_with_origins` does, starting from the moved value.
+    fn rotate_with_wrap_index(len: usize, pos: usize, offset: i64) -> Vec<usize> {
+        let mut values = (0..len).collect::<Vec<_>>();
+        let value = values.remove(pos);
+        let next_index = wrap_index(pos as i64, offset, len as i64);
+
+        if next_index == 0 {
+            values.push(value);
+        } else {
+            values.insert(next_index, value);
+        }
+
+        let position = values.iter().position(|other| other == &value).unwrap();
+        values.rotate_left(position);
+        values
+    }
+
+    #[test]
+    fn wrap_index_matches_naive_rotation() {
+        for len in 2..=7 {
+            for pos in 0..len {
+                for offset in -50..=50 {
+                    assert_eq!(
+                        rotate_with_wrap_index(len, pos, offset),
+                        rotate_naively(len, pos, offset),
+                        "moving position {pos} by {offset} in a list of {len}"
+                    );
+                }
+            }
+        }
+    }
 
-    println!("{sum_coords:?}");
+    #[test]
+    fn wrap_index_of_large_offsets() {
+        for len in 2..=7i64 {
+            for pos in 0..len {
+                for offset in -20..=20 {
+                    // Every `len - 1` places moved is a full lap of the other values.
+                    for laps in [811_589_153, 1_000_000_007, i64::MAX / 16] {
+                        let laps = laps / (len - 1) * (len - 1);
+
+                        assert_eq!(
+                            wrap_index(pos, offset + laps, len),
+                            wrap_index(pos, offset, len)
+                        );
+                        assert_eq!(
+                            wrap_index(pos, offset - laps, len),
+                            wrap_index(pos, offset, len)
+                        );
+                    }
+                }
+            }
+        }
+
+        // A single value never moves.
+        assert_eq!(wrap_index(0, i64::MAX / 2, 1), 0);
+    }
+
+    #[test]
+    fn answers_match_the_reference() {
+        let coords = parse_lines::<i64>("1\n2\n-3\n3\n-2\n0\n4\n").unwrap();
+        let decrypted = coords
+            .iter()
+            .map(|coord| coord * 811_589_153)
+            .collect::<Vec<_>>();
+
+        assert_eq!(get_coords(&mix(&coords, 1)), part1_reference(&coords));
+        assert_eq!(get_coords(&mix(&decrypted, 10)), part2_reference(&coords));
+
+        // Ten rounds of the reference on the puzzle input take too long for a test, so only the
+        // first part is checked here and the second is left to `--validate-answers`.
+        let coords = parse_lines::<i64>(include_str!("../input.txt")).unwrap();
+
+        assert_eq!(get_coords(&mix(&coords, 1)), part1_reference(&coords));
+    }
+
+    #[test]
+    fn duplicates_keep_their_origins() {
+        let coords = [2, 0, 2, -1, 2, -1];
+
+        for rounds in [1, 2, 10] {
+            let mixed = mix_with_origins(&coords, rounds);
+
+            // Every origin shows up once, with the value it had in the slice.
+            let mut origins = mixed.iter().map(|(_, origin)| *origin).collect::<Vec<_>>();
+            origins.sort();
+            assert_eq!(origins, (0..coords.len()).collect::<Vec<_>>());
+            assert!(mixed
+                .iter()
+                .all(|(value, origin)| coords[*origin] == *value));
+
+            // The mixed list is circular, so compare both starting from the zero.
+            let from_zero = |mut values: Vec<i64>| {
+                let zero_index = values.iter().position(|value| value == &0).unwrap();
+                values.rotate_left(zero_index);
+                values
+            };
+
+            assert_eq!(
+                from_zero(mix(&coords, rounds)),
+                from_zero(mix_reference(&coords, rounds))
+            );
+        }
+    }
 }
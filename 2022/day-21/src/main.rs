@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use aoc_common::{flag_value, input_filename, print_answers, read_input};
+
 /// An enum that represents a arithmetic operation that a monkey could yell out.
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Operation {
@@ -112,15 +114,15 @@ impl Monkey {
         // We try to update the monkey in our monkey map in case we were able to calculate the
         // value and the monkey was a math monkey, otherwise we continue.
         if let Self::Math(..) = left_monkey {
-            if left_value.is_some() {
-                monkeys.insert(left.to_string(), Self::Number(left_value.unwrap()));
+            if let Some(value) = left_value {
+                monkeys.insert(left.to_string(), Self::Number(value));
             }
         }
 
         // We do the same for the right monkey.
         if let Self::Math(..) = right_monkey {
-            if right_value.is_some() {
-                monkeys.insert(right.to_string(), Self::Number(right_value.unwrap()));
+            if let Some(value) = right_value {
+                monkeys.insert(right.to_string(), Self::Number(value));
             }
         }
 
@@ -163,10 +165,9 @@ impl Monkey {
     }
 }
 
-/// Get the monkeys and what they are yelling from the input file into a hash map.
-fn read_monkeys(filename: &str) -> HashMap<String, Monkey> {
-    std::fs::read_to_string(filename)
-        .unwrap()
+/// Parse the monkeys and what they are yelling from the input into a hash map.
+fn parse_monkeys(input: &str) -> HashMap<String, Monkey> {
+    input
         .lines()
         .map(|line| {
             let mut split = line.split(": ");
@@ -179,44 +180,172 @@ fn read_monkeys(filename: &str) -> HashMap<String, Monkey> {
         .collect()
 }
 
+/// Get the monkeys and what they are yelling from the input file into a hash map.
+fn read_monkeys(filename: &str) -> HashMap<String, Monkey> {
+    parse_monkeys(&read_input(filename).unwrap())
+}
+
+/// Check if the monkey with the given name is the human, or uses what the human yells somewhere in
+/// their calculations.
+fn contains_human(name: &str, monkeys: &HashMap<String, Monkey>) -> bool {
+    match monkeys.get(name) {
+        _ if name == "humn" => true,
+        Some(Monkey::Math(left, _, right)) => {
+            contains_human(left, monkeys) || contains_human(right, monkeys)
+        }
+        _ => false,
+    }
+}
+
+/// The operands of the root monkey, split into the side which uses the human and the other side.
+struct RootSides<'a> {
+    human_side: &'a str,
+    other_side: &'a str,
+    operation: &'a Operation,
+    human_on_left: bool,
+}
+
+/// Split the operands of the root monkey into the side which uses the human and the other side.
+fn root_sides(monkeys: &HashMap<String, Monkey>) -> RootSides<'_> {
+    // Get the left and right operands of what the root monkey is yelling.
+    let Some(Monkey::Math(left, operation, right)) = monkeys.get("root") else {
+        panic!("The root monkey should be yelling a math operation");
+    };
+
+    // Find the side which uses the human.
+    let human_on_left = contains_human(left, monkeys);
+    let (human_side, other_side) = if human_on_left {
+        (left, right)
+    } else {
+        (right, left)
+    };
+
+    RootSides {
+        human_side,
+        other_side,
+        operation,
+        human_on_left,
+    }
+}
+
+/// Get the value the monkey with the given name is yelling. We calculate it on a clone, since
+/// calculating values replaces monkeys by the numbers they yell.
+fn value_of(name: &str, monkeys: &HashMap<String, Monkey>) -> i64 {
+    monkeys[name]
+        .clone()
+        .get_value(&mut monkeys.clone())
+        .unwrap()
+}
+
+/// Find the value the human has to yell so that the monkey with the given name, which uses the
+/// human, yells the `target` value.
+fn adjust_human(human_side: &str, monkeys: &HashMap<String, Monkey>, target: i64) -> i64 {
+    // Insert a placeholder at the `humn` monkey position.
+    let mut monkeys = monkeys.clone();
+    monkeys.insert("humn".to_string(), Monkey::Human);
+
+    // Calculate as much as we can of the human's side, so that every monkey not using the human
+    // is replaced by the number it yells.
+    let human_tree = monkeys[human_side].clone();
+    human_tree.get_value(&mut monkeys);
+
+    // Find the adjusted value inside the human's side that would make its value equal the target.
+    human_tree.adjust(&monkeys, target)
+}
+
+/// Get the value of the root monkey.
+fn solve_root(monkeys: &HashMap<String, Monkey>) -> i64 {
+    value_of("root", monkeys)
+}
+
+/// Find the value the human has to yell so that both operands of the root monkey are equal. Only
+/// one side of the root monkey uses the human, so we calculate the other side and adjust the
+/// human's side to match it.
+fn solve_humn(monkeys: &HashMap<String, Monkey>) -> i64 {
+    let sides = root_sides(monkeys);
+
+    adjust_human(
+        sides.human_side,
+        monkeys,
+        value_of(sides.other_side, monkeys),
+    )
+}
+
+/// Get the value of the root monkey and the value the human has to yell so that both operands of
+/// the root monkey are equal. The side of the root monkey without the human is only calculated
+/// once and used for both - combined with the human's side for the root's value, and as the
+/// target the human's side is adjusted to match.
+fn solve(monkeys: &HashMap<String, Monkey>) -> (i64, i64) {
+    let sides = root_sides(monkeys);
+
+    let target = value_of(sides.other_side, monkeys);
+    let human_value = value_of(sides.human_side, monkeys);
+
+    let number = if sides.human_on_left {
+        sides.operation.perform(human_value, target)
+    } else {
+        sides.operation.perform(target, human_value)
+    };
+
+    (number, adjust_human(sides.human_side, monkeys, target))
+}
+
 fn main() {
     // Get the monkeys.
-    let mut monkeys = read_monkeys("input.txt");
+    let monkeys = read_monkeys(input_filename());
+
+    // Solve only one of the parts if requested.
+    if let Some(part) = flag_value("--part") {
+        match part.as_deref() {
+            Some("1") => println!("{}", solve_root(&monkeys)),
+            Some("2") => println!("{}", solve_humn(&monkeys)),
+            _ => {
+                eprintln!("The --part flag expects 1 or 2");
+                std::process::exit(1);
+            }
+        }
 
-    // Clone the monkeys to save the original equations.
-    let mut clone = monkeys.clone();
+        return;
+    }
 
-    // Get the root monkey.
-    let root_monkey = monkeys.get("root").unwrap().clone();
+    // Get the value of the root monkey and the value the human has to yell for the root monkey's
+    // operands to be equal.
+    let (number, adjusted) = solve(&monkeys);
 
-    // Get the value of the root monkey.
-    let number = root_monkey.get_value(&mut clone).unwrap();
+    print_answers(21, number, adjusted);
+}
 
-    println!("{number}");
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Get the left and right operands of what the root monkey is yelling.
-    if let Monkey::Math(left, _, right) = root_monkey {
-        // Insert a placeholder at the `humn` monkey position.
-        monkeys.insert("humn".to_string(), Monkey::Human);
-
-        // Get the values of the left and right operands we calculated earlier.
-        let left_value = clone.get(&left).unwrap().clone();
-        let right_value = clone.get(&right).unwrap().clone();
-
-        // Get the monkeys from the original input.
-        let left_tree = monkeys.get(&left).unwrap().clone();
-        let right_tree = monkeys.get(&right).unwrap().clone();
-
-        // If the left monkey uses a placeholder somewhere in their calculations, we find the
-        // adjusted value inside that subtree that would make the subtree's value equal the right
-        // subtree's value.
-        let adjusted = if left_tree.get_value(&mut monkeys).is_none() {
-            left_tree.adjust(&monkeys, right_value.get_value(&mut clone).unwrap())
-        // We do the same process for the right subtree if the left didn't use the placeholder.
-        } else {
-            right_tree.adjust(&monkeys, left_value.get_value(&mut clone).unwrap())
-        };
+    const EXAMPLE: &str =
+        "root: pppw + sjmn\ndbpl: 5\ncczh: sllz + lgvd\nzczc: 2\nptdq: humn - dvpt\n\
+                           dvpt: 3\nlfqf: 4\nhumn: 5\nljgn: 2\nsjmn: drzm * dbpl\nsllz: 4\n\
+                           pppw: cczh / lfqf\nlgvd: ljgn * ptdq\ndrzm: hmdt - zczc\nhmdt: 32\n";
+
+    #[test]
+    fn solve_matches_the_separate_parts() {
+        for input in [EXAMPLE, include_str!("../input.txt")] {
+            let monkeys = parse_monkeys(input);
+
+            assert_eq!(
+                solve(&monkeys),
+                (solve_root(&monkeys), solve_humn(&monkeys))
+            );
+        }
+
+        assert_eq!(solve(&parse_monkeys(EXAMPLE)), (152, 301));
+    }
+
+    #[test]
+    fn solve_humn_from_a_fresh_parse() {
+        let monkeys = parse_monkeys(EXAMPLE);
 
-        println!("{adjusted}");
+        assert!(contains_human("ptdq", &monkeys));
+        assert!(!contains_human("sjmn", &monkeys));
+        assert_eq!(solve_humn(&monkeys), 301);
+        // Solving doesn't change the parsed monkeys.
+        assert_eq!(monkeys, parse_monkeys(EXAMPLE));
     }
 }